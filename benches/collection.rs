@@ -0,0 +1,140 @@
+//! Compares scanning a collection of 100k entries with looking entries up in its indexes.
+//! Run with `cargo bench --bench collection`.
+
+use std::{fs, hint::black_box, path::Path};
+
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use serde_json::json;
+use tempfile::TempDir;
+use todo_list_backend::{
+    models::{entry::Entry, list::List},
+    prototype_db::{Collection, Database, JournalOptions, ID_INDEX, LIST_ID_INDEX},
+    storage::{EntryStore, ListStore},
+};
+
+const LISTS: usize = 100;
+const ENTRIES_PER_LIST: usize = 1000;
+
+/// Writes the collection file directly, appending 100k entries one by one takes too long.
+fn write_collection<T: serde::Serialize>(dir: &Path, name: &str, data: &[T]) {
+    let container = json!({ "count": data.len(), "sequence": 0, "data": data });
+    fs::write(
+        dir.join(format!("{}.json", name)),
+        serde_json::to_vec(&container).unwrap(),
+    )
+    .unwrap();
+}
+
+/// Creates a database of `LISTS` lists with `ENTRIES_PER_LIST` entries each,
+/// the entries of the lists are interleaved like they are when they are added over time.
+fn seed() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let now = Utc::now();
+    let lists: Vec<List> = (0..LISTS)
+        .map(|list| List {
+            id: format!("list-{}", list),
+            name: format!("list {}", list),
+            owner_id: None,
+            created_at: now,
+            updated_at: now,
+        })
+        .collect();
+    let entries: Vec<Entry> = (0..ENTRIES_PER_LIST)
+        .flat_map(|entry| (0..LISTS).map(move |list| (list, entry)))
+        .map(|(list, entry)| Entry {
+            id: format!("entry-{}-{}", list, entry),
+            list_id: format!("list-{}", list),
+            parent_id: None,
+            name: format!("entry {}", entry),
+            done: false,
+            position: (entry as i64 + 1) * 1024,
+            due_at: None,
+            priority: None,
+            notes: None,
+            recurrence: None,
+            created_at: now,
+            updated_at: now,
+        })
+        .collect();
+    write_collection(dir.path(), "list", &lists);
+    write_collection(dir.path(), "entry", &entries);
+    dir
+}
+
+fn journal_options() -> Option<JournalOptions> {
+    // compacting would rewrite all 100k entries in the middle of a measurement
+    Some(JournalOptions {
+        compact_after: usize::MAX,
+    })
+}
+
+fn bench_collection(c: &mut Criterion) {
+    let dir = seed();
+    let entry_collection: Collection<Entry> =
+        Collection::new("entry", dir.path().to_str().unwrap(), journal_options())
+            .unwrap()
+            .with_unique_index(ID_INDEX, |model: &Entry| model.id.clone())
+            .with_index(LIST_ID_INDEX, |model: &Entry| model.list_id.clone());
+    // the last entry is the worst case for a scan
+    let id = format!("entry-{}-{}", LISTS - 1, ENTRIES_PER_LIST - 1);
+    let list_id = format!("list-{}", LISTS / 2);
+
+    let mut group = c.benchmark_group("find entry by id");
+    group.bench_function("scan", |b| {
+        b.iter(|| entry_collection.find_one(|model| model.id == *black_box(&id)))
+    });
+    group.bench_function("index", |b| {
+        b.iter(|| entry_collection.find_one_by_key(ID_INDEX, black_box(&id)))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("find entries of list");
+    group.bench_function("scan", |b| {
+        b.iter(|| entry_collection.find(|model| model.list_id == *black_box(&list_id)))
+    });
+    group.bench_function("index", |b| {
+        b.iter(|| entry_collection.find_by_key(LIST_ID_INDEX, black_box(&list_id)))
+    });
+    group.finish();
+
+    let mut entry_collection = entry_collection;
+    let mut group = c.benchmark_group("patch entry by id");
+    group.bench_function("scan", |b| {
+        b.iter(|| {
+            entry_collection
+                .patch_one(|model| model.id == id, |model| model.done = !model.done)
+                .unwrap()
+        })
+    });
+    group.bench_function("index", |b| {
+        b.iter(|| {
+            entry_collection
+                .patch_one_by_key(ID_INDEX, &id, |model| model.done = !model.done)
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+/// What `GET /lists/{id}/entries` does with the json backend.
+fn bench_store(c: &mut Criterion) {
+    let dir = seed();
+    let database =
+        Database::new(dir.path().to_str().unwrap().to_string(), journal_options()).unwrap();
+    let mut group = c.benchmark_group("list and its entries");
+    for list in [0, LISTS - 1] {
+        let list_id = format!("list-{}", list);
+        group.bench_with_input(BenchmarkId::from_parameter(&list_id), &list_id, |b, id| {
+            b.iter(|| {
+                let list = database.find_list(id).unwrap();
+                let entries = database.find_entries_of_list(id).unwrap();
+                (list, entries)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_collection, bench_store);
+criterion_main!(benches);
@@ -0,0 +1,228 @@
+use std::{env, ffi::OsString, fmt, fs, io, net::IpAddr, path::PathBuf};
+
+use clap::{builder::BoolishValueParser, Parser};
+use serde::Deserialize;
+
+use crate::{prototype_db::JournalOptions, storage::StorageBackend};
+
+/// Every setting can be given as a command line flag, as a `TODO_*` environment variable
+/// or in the config file. Flags take precedence over environment variables,
+/// which take precedence over the config file, which takes precedence over the defaults.
+#[derive(Parser)]
+#[command(about = "todo list backend")]
+struct Args {
+    /// optional TOML config file
+    #[arg(long, env = "TODO_CONFIG")]
+    config: Option<PathBuf>,
+    /// address to listen on [default: 0.0.0.0]
+    #[arg(long, env = "TODO_BIND_ADDRESS")]
+    bind_address: Option<String>,
+    /// port to listen on [default: 1337]
+    #[arg(long, env = "TODO_PORT")]
+    port: Option<u16>,
+    /// path all api routes are mounted under [default: /api]
+    #[arg(long, env = "TODO_API_PREFIX")]
+    api_prefix: Option<String>,
+    /// directory the database files are stored in [default: db]
+    #[arg(long, env = "TODO_DB_DIR")]
+    db_dir: Option<String>,
+    /// storage backend, "json" or "sqlite" [default: json]
+    #[arg(long, env = "TODO_STORAGE")]
+    storage: Option<StorageBackend>,
+    /// append mutations of the json backend to a journal [default: false]
+    #[arg(long, env = "TODO_JOURNAL", value_parser = BoolishValueParser::new())]
+    journal: Option<bool>,
+    /// number of journaled operations after which the journal is compacted [default: 1000]
+    #[arg(long, env = "TODO_JOURNAL_COMPACT_AFTER")]
+    journal_compact_after: Option<usize>,
+    /// comma separated origins allowed by CORS, "*" allows any origin [default: *]
+    #[arg(long, env = "TODO_CORS_ALLOWED_ORIGINS", value_delimiter = ',')]
+    cors_allowed_origins: Option<Vec<String>>,
+    /// name of a registered user who becomes the owner of the lists and tags from before
+    /// there were users when the server starts
+    #[arg(long, env = "TODO_CLAIM_OWNERLESS")]
+    claim_ownerless: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    bind_address: Option<String>,
+    port: Option<u16>,
+    api_prefix: Option<String>,
+    db_dir: Option<String>,
+    storage: Option<StorageBackend>,
+    journal: Option<bool>,
+    journal_compact_after: Option<usize>,
+    cors_allowed_origins: Option<Vec<String>>,
+    claim_ownerless: Option<String>,
+}
+
+pub struct Config {
+    pub bind_address: String,
+    pub port: u16,
+    pub api_prefix: String,
+    pub db_dir: String,
+    pub storage: StorageBackend,
+    pub journal_options: Option<JournalOptions>,
+    /// `None` allows any origin
+    pub cors_allowed_origins: Option<Vec<String>>,
+    /// name of the user who gets the lists and tags that have no owner
+    pub claim_ownerless: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read {
+        path: PathBuf,
+        error: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: toml::de::Error,
+    },
+    Invalid {
+        setting: &'static str,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read { path, error } => {
+                write!(f, "could not read {}: {}", path.display(), error)
+            }
+            ConfigError::Parse { path, error } => {
+                write!(f, "could not parse {}: {}", path.display(), error)
+            }
+            ConfigError::Invalid { setting, message } => {
+                write!(f, "invalid {}: {}", setting, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn invalid(setting: &'static str, message: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        setting,
+        message: message.into(),
+    }
+}
+
+impl Config {
+    /// Reads the configuration from the command line, the environment and the config file.
+    /// Exits the process if the command line cannot be parsed, like for `--help`.
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::load_from(env::args_os())
+    }
+
+    /// Like `load`, with the given command line instead of the one of the process.
+    /// The first argument is the name of the program.
+    pub fn load_from<I, T>(command_line: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let args = Args::parse_from(command_line);
+        let file_config = match &args.config {
+            Some(path) => {
+                let contents = fs::read_to_string(path).map_err(|error| ConfigError::Read {
+                    path: path.clone(),
+                    error,
+                })?;
+                toml::from_str(&contents).map_err(|error| ConfigError::Parse {
+                    path: path.clone(),
+                    error,
+                })?
+            }
+            None => FileConfig::default(),
+        };
+        Self::merge(args, file_config)
+    }
+
+    fn merge(args: Args, file_config: FileConfig) -> Result<Self, ConfigError> {
+        let bind_address = args
+            .bind_address
+            .or(file_config.bind_address)
+            .unwrap_or_else(|| "0.0.0.0".to_string());
+        if bind_address.parse::<IpAddr>().is_err() {
+            return Err(invalid(
+                "bind_address",
+                format!("\"{}\" is not an ip address", bind_address),
+            ));
+        }
+
+        let port = args.port.or(file_config.port).unwrap_or(1337);
+
+        let api_prefix = args
+            .api_prefix
+            .or(file_config.api_prefix)
+            .unwrap_or_else(|| "/api".to_string());
+        if !api_prefix.starts_with('/') || api_prefix.ends_with('/') {
+            return Err(invalid(
+                "api_prefix",
+                "must start with a slash and must not end with one, e.g. /api",
+            ));
+        }
+
+        let db_dir = args
+            .db_dir
+            .or(file_config.db_dir)
+            .unwrap_or_else(|| "db".to_string());
+        if db_dir.is_empty() {
+            return Err(invalid("db_dir", "must not be empty"));
+        }
+
+        let storage = args
+            .storage
+            .or(file_config.storage)
+            .unwrap_or(StorageBackend::Json);
+
+        let journal = args.journal.or(file_config.journal).unwrap_or(false);
+        let journal_compact_after = args
+            .journal_compact_after
+            .or(file_config.journal_compact_after)
+            .unwrap_or(JournalOptions::default().compact_after);
+        if journal_compact_after == 0 {
+            return Err(invalid("journal_compact_after", "must be at least 1"));
+        }
+        let journal_options = journal.then_some(JournalOptions {
+            compact_after: journal_compact_after,
+        });
+
+        let cors_allowed_origins = args
+            .cors_allowed_origins
+            .or(file_config.cors_allowed_origins)
+            .filter(|origins| !origins.iter().any(|origin| origin == "*"));
+        if let Some(origins) = &cors_allowed_origins {
+            if let Some(origin) = origins
+                .iter()
+                .find(|origin| !origin.starts_with("http://") && !origin.starts_with("https://"))
+            {
+                return Err(invalid(
+                    "cors_allowed_origins",
+                    format!("\"{}\" is not an http or https origin", origin),
+                ));
+            }
+        }
+
+        let claim_ownerless = args.claim_ownerless.or(file_config.claim_ownerless);
+        if claim_ownerless.as_deref().is_some_and(str::is_empty) {
+            return Err(invalid("claim_ownerless", "must not be empty"));
+        }
+
+        Ok(Self {
+            bind_address,
+            port,
+            api_prefix,
+            db_dir,
+            storage,
+            journal_options,
+            cors_allowed_origins,
+            claim_ownerless,
+        })
+    }
+}
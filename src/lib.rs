@@ -0,0 +1,43 @@
+use actix_web::{
+    web::{self, ServiceConfig},
+    Responder, Scope,
+};
+
+pub mod config;
+pub mod models;
+pub mod prototype_db;
+pub mod routes;
+pub mod storage;
+
+async fn get_api_index() -> impl Responder {
+    "welcome to my api"
+}
+
+/// Registers all routes below `api_prefix` together with the extractor configs they rely on.
+/// The store has to be added as `web::Data<dyn Store>` by the caller.
+pub fn configure_api(config: &mut ServiceConfig, api_prefix: &str) {
+    let auth_scope =
+        Scope::new(&format!("{}/auth", api_prefix)).configure(routes::auth::configure_routes);
+    let entries_scope =
+        Scope::new(&format!("{}/entries", api_prefix)).configure(routes::entry::configure_routes);
+    let lists_scope =
+        Scope::new(&format!("{}/lists", api_prefix)).configure(routes::list::configure_routes);
+    let members_scope = Scope::new(&format!("{}/lists/{{list_id}}/members", api_prefix))
+        .configure(routes::member::configure_routes);
+    let search_scope =
+        Scope::new(&format!("{}/search", api_prefix)).configure(routes::search::configure_routes);
+    let tags_scope =
+        Scope::new(&format!("{}/tags", api_prefix)).configure(routes::tag::configure_routes);
+
+    config
+        .app_data(web::JsonConfig::default().error_handler(routes::error::json_error_handler))
+        .app_data(web::QueryConfig::default().error_handler(routes::error::query_error_handler))
+        .route(api_prefix, web::get().to(get_api_index))
+        .service(auth_scope)
+        // registered before the lists, whose scope would match the members as well
+        .service(members_scope)
+        .service(lists_scope)
+        .service(entries_scope)
+        .service(search_scope)
+        .service(tags_scope);
+}
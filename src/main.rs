@@ -1,46 +1,83 @@
-use actix_cors::Cors;
-use actix_web::{middleware, web, App, HttpServer, Responder, Scope};
+use std::process;
 
-mod models;
-mod prototype_db;
-mod routes;
+use actix_cors::Cors;
+use actix_web::{middleware, web, App, HttpServer};
 
-async fn get_api_index() -> impl Responder {
-    "welcome to my api"
-}
+use todo_list_backend::{config, configure_api, storage};
 
 #[actix_web::main]
 async fn main() {
-    let bind_address = "0.0.0.0";
-    let port = 1337;
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
-    let api_prefix = "/api";
+    let config = match config::Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("{}", e);
+            process::exit(2);
+        }
+    };
 
-    let app_data = web::Data::new(prototype_db::Database::new("db".to_string()).unwrap());
+    let store = match storage::open(config.storage, &config.db_dir, config.journal_options) {
+        Ok(store) => store,
+        Err(e) => {
+            log::error!("could not open the database: {}", e);
+            process::exit(1);
+        }
+    };
 
-    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+    if let Some(user_name) = &config.claim_ownerless {
+        match storage::claim_ownerless(&*store, user_name) {
+            Ok(Some((lists, tags))) => log::info!(
+                "{} now owns {} lists and {} tags that had no owner",
+                user_name,
+                lists,
+                tags
+            ),
+            Ok(None) => {
+                log::error!("invalid claim_ownerless: there is no user {}", user_name);
+                process::exit(2);
+            }
+            Err(e) => {
+                log::error!("could not claim the lists and tags without owner: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+    let app_data = web::Data::from(store);
+
+    let api_prefix = config.api_prefix;
+    let cors_allowed_origins = config.cors_allowed_origins;
+
+    let bind_address = config.bind_address;
+    let port = config.port;
 
     println!("Listening on {}:{}", bind_address, port);
-    HttpServer::new(move || {
-        let entries_scope = Scope::new(&format!("{}/entries", api_prefix))
-            .configure(routes::entry::configure_routes);
-        let lists_scope =
-            Scope::new(&format!("{}/lists", api_prefix)).configure(routes::list::configure_routes);
+    let server = HttpServer::new(move || {
+        let cors = match &cors_allowed_origins {
+            Some(origins) => origins
+                .iter()
+                .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+                .allow_any_method()
+                .allow_any_header(),
+            None => Cors::permissive(),
+        };
 
         App::new()
             .app_data(app_data.clone())
+            .configure(|config| configure_api(config, &api_prefix))
             .wrap(middleware::NormalizePath::new(
                 middleware::TrailingSlash::Trim,
             ))
             .wrap(middleware::Logger::default())
-            .wrap(Cors::permissive())
-            .route(&format!("{}", api_prefix), web::get().to(get_api_index))
-            .service(lists_scope)
-            .service(entries_scope)
+            .wrap(cors)
     })
-    .bind((bind_address, port))
-    .unwrap()
-    .run()
-    .await
-    .unwrap()
+    .bind((bind_address.as_str(), port));
+    let server = match server {
+        Ok(server) => server,
+        Err(e) => {
+            log::error!("could not listen on {}:{}: {}", bind_address, port, e);
+            process::exit(1);
+        }
+    };
+    server.run().await.unwrap()
 }
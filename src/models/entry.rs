@@ -1,12 +1,39 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::recurrence::Recurrence;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+    Urgent,
+}
+
+// must come before the derive to take effect
+#[serde_with::skip_serializing_none]
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-#[serde_with::skip_serializing_none]
 pub struct Entry {
     #[serde(rename = "_id")]
     pub id: String,
     pub list_id: String,
+    /// the entry this is a sub-entry of, which belongs to the same list
+    pub parent_id: Option<String>,
     pub name: String,
     pub done: bool,
+    /// orders the entries of a list, see `storage::position`
+    #[serde(default)]
+    pub position: i64,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<Priority>,
+    pub notes: Option<String>,
+    /// when the entry is done, its next occurrence is added following this rule
+    pub recurrence: Option<Recurrence>,
+    #[serde(default = "super::legacy_timestamp")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "super::legacy_timestamp")]
+    pub updated_at: DateTime<Utc>,
 }
@@ -1,10 +1,18 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+#[serde_with::skip_serializing_none]
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
-#[serde_with::skip_serializing_none]
 pub struct List {
     #[serde(rename = "_id")]
     pub id: String,
     pub name: String,
+    /// the user who created the list, lists from before there were users have none
+    /// and nobody gets to see them until a user claims them with `claim_ownerless`
+    pub owner_id: Option<String>,
+    #[serde(default = "super::legacy_timestamp")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default = "super::legacy_timestamp")]
+    pub updated_at: DateTime<Utc>,
 }
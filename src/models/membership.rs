@@ -0,0 +1,39 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// What a user may do with a list, every role may do everything the ones before it may.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// sees the list and its entries
+    Viewer,
+    /// also changes the list and its entries
+    Editor,
+    /// also deletes the list and decides who else has access to it
+    Owner,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Owner => "owner",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Gives a user access to a list they don't own.
+/// The user who created the list is its owner without a membership.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Membership {
+    pub list_id: String,
+    pub user_id: String,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
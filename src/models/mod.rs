@@ -1,3 +1,15 @@
+use chrono::{DateTime, TimeZone, Utc};
+
 pub mod entry;
 pub mod list;
+pub mod membership;
 pub mod parent_and_children;
+pub mod recurrence;
+pub mod tag;
+pub mod user;
+
+/// Timestamp of records that were stored before timestamps were introduced.
+/// Using the epoch sorts them before everything that was created afterwards.
+pub fn legacy_timestamp() -> DateTime<Utc> {
+    Utc.timestamp_opt(0, 0).unwrap()
+}
@@ -9,3 +9,21 @@ where
     pub parent: T,
     pub children: &'a [U],
 }
+
+/// A recursive `ParentAndChildren`, every child is a tree of its own.
+#[derive(Serialize)]
+pub struct ParentAndChildrenTree<T>
+where
+    T: Serialize,
+{
+    pub parent: T,
+    pub progress: Progress,
+    pub children: Vec<ParentAndChildrenTree<T>>,
+}
+
+/// How many of the direct children are done.
+#[derive(Serialize)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
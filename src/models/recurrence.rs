@@ -0,0 +1,389 @@
+//! Rules for entries that recur, like "every other week on monday and friday".
+//! Everything here is pure, the routes decide when an occurrence is due.
+
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc, Weekday};
+use serde::{
+    de::{self, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+/// Monthly and yearly rules skip dates that don't exist, like the 31st of april,
+/// but never more often than this in a row.
+const MAX_SKIPPED: u32 = 100;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// Can be given as an object or as a string in a subset of the RRULE syntax of RFC 5545,
+/// e.g. `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR`. It is always stored as an object.
+#[serde_with::skip_serializing_none]
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// every how many days, weeks, months or years the entry recurs
+    pub interval: u32,
+    /// the days of the week a weekly rule recurs on, empty means every `interval` weeks
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub weekdays: Vec<Weekday>,
+    /// no occurrence is due after this
+    pub until: Option<DateTime<Utc>>,
+    /// the number of occurrences left, including the current one
+    pub count: Option<u32>,
+}
+
+/// The object form of a `Recurrence`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RecurrenceObject {
+    frequency: Frequency,
+    #[serde(default = "default_interval")]
+    interval: u32,
+    #[serde(default)]
+    weekdays: Vec<Weekday>,
+    until: Option<DateTime<Utc>>,
+    count: Option<u32>,
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+impl Recurrence {
+    /// Returns when the occurrence after the one at `due_at` is due, together with the
+    /// rule that occurrence carries on with, or `None` if the rule has ended.
+    /// The time of day stays the same and days are counted in UTC.
+    pub fn next(&self, due_at: DateTime<Utc>) -> Option<(DateTime<Utc>, Recurrence)> {
+        if self.count.is_some_and(|count| count <= 1) {
+            return None;
+        }
+        let date = self.next_date(due_at.date_naive())?;
+        let next_due_at = Utc.from_utc_datetime(&date.and_time(due_at.time()));
+        if self.until.is_some_and(|until| next_due_at > until) {
+            return None;
+        }
+        let mut rule = self.clone();
+        rule.count = self.count.map(|count| count - 1);
+        Some((next_due_at, rule))
+    }
+
+    fn next_date(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let interval = self.interval;
+        match self.frequency {
+            Frequency::Daily => Some(date + Duration::days(interval.into())),
+            Frequency::Weekly if self.weekdays.is_empty() => {
+                Some(date + Duration::weeks(interval.into()))
+            }
+            Frequency::Weekly => {
+                // weeks start on monday, like RRULE's default WKST
+                let weekday = date.weekday().num_days_from_monday();
+                let days = self
+                    .weekdays
+                    .iter()
+                    .map(|weekday| weekday.num_days_from_monday());
+                if let Some(later) = days.clone().filter(|day| *day > weekday).min() {
+                    return Some(date + Duration::days((later - weekday).into()));
+                }
+                let monday = date - Duration::days(weekday.into());
+                let first = days.min()?;
+                Some(monday + Duration::weeks(interval.into()) + Duration::days(first.into()))
+            }
+            Frequency::Monthly => {
+                (1..=MAX_SKIPPED).find_map(|step| add_months(date, interval * step))
+            }
+            Frequency::Yearly => {
+                (1..=MAX_SKIPPED).find_map(|step| add_months(date, 12 * interval * step))
+            }
+        }
+    }
+}
+
+/// Returns the same day of the month `months` later, or `None` if that month is too short.
+fn add_months(date: NaiveDate, months: u32) -> Option<NaiveDate> {
+    let month = i64::from(date.year()) * 12 + i64::from(date.month0()) + i64::from(months);
+    let year = i32::try_from(month.div_euclid(12)).ok()?;
+    NaiveDate::from_ymd_opt(year, month.rem_euclid(12) as u32 + 1, date.day())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseRuleError(String);
+
+impl fmt::Display for ParseRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid recurrence rule: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseRuleError {}
+
+fn parse_error(message: impl Into<String>) -> ParseRuleError {
+    ParseRuleError(message.into())
+}
+
+fn parse_weekday(code: &str) -> Result<Weekday, ParseRuleError> {
+    match code {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => Err(parse_error(format!("unsupported BYDAY value \"{}\"", code))),
+    }
+}
+
+/// Parses UNTIL, either as a date, which includes the whole day, or as a UTC date-time.
+fn parse_until(value: &str) -> Result<DateTime<Utc>, ParseRuleError> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        let end_of_day = date.and_hms_opt(23, 59, 59).unwrap();
+        return Ok(Utc.from_utc_datetime(&end_of_day));
+    }
+    chrono::NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+        .map(|date_time| Utc.from_utc_datetime(&date_time))
+        .map_err(|_| {
+            parse_error(format!(
+                "UNTIL \"{}\" is neither a date nor a UTC date-time",
+                value
+            ))
+        })
+}
+
+fn parse_number(key: &str, value: &str) -> Result<u32, ParseRuleError> {
+    value
+        .parse()
+        .map_err(|_| parse_error(format!("{} \"{}\" is not a number", key, value)))
+}
+
+impl FromStr for Recurrence {
+    type Err = ParseRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim();
+        let text = text.strip_prefix("RRULE:").unwrap_or(text);
+        let mut frequency = None;
+        let mut rule = Recurrence {
+            frequency: Frequency::Daily,
+            interval: default_interval(),
+            weekdays: Vec::new(),
+            until: None,
+            count: None,
+        };
+        for part in text.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| parse_error(format!("\"{}\" is not of the form KEY=VALUE", part)))?;
+            match key {
+                "FREQ" => {
+                    frequency = Some(match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(parse_error(format!("unsupported FREQ \"{}\"", value))),
+                    })
+                }
+                "INTERVAL" => rule.interval = parse_number(key, value)?,
+                "COUNT" => rule.count = Some(parse_number(key, value)?),
+                "UNTIL" => rule.until = Some(parse_until(value)?),
+                "BYDAY" => {
+                    rule.weekdays = value
+                        .split(',')
+                        .map(parse_weekday)
+                        .collect::<Result<_, _>>()?
+                }
+                _ => return Err(parse_error(format!("unsupported part \"{}\"", key))),
+            }
+        }
+        rule.frequency = frequency.ok_or_else(|| parse_error("FREQ is missing"))?;
+        Ok(rule)
+    }
+}
+
+impl<'de> Deserialize<'de> for Recurrence {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RecurrenceVisitor;
+
+        impl<'de> Visitor<'de> for RecurrenceVisitor {
+            type Value = Recurrence;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a recurrence rule object or an RRULE string")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                value.parse().map_err(E::custom)
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let object =
+                    RecurrenceObject::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                Ok(Recurrence {
+                    frequency: object.frequency,
+                    interval: object.interval,
+                    weekdays: object.weekdays,
+                    until: object.until,
+                    count: object.count,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(RecurrenceVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 9, 30, 0).unwrap()
+    }
+
+    fn rule(text: &str) -> Recurrence {
+        text.parse().unwrap()
+    }
+
+    /// The due dates of the occurrences after `due_at`, until the rule ends or `limit` is reached.
+    fn occurrences(rule: Recurrence, due_at: DateTime<Utc>, limit: usize) -> Vec<DateTime<Utc>> {
+        let mut dates = Vec::new();
+        let (mut due_at, mut rule) = (due_at, rule);
+        while dates.len() < limit {
+            let Some((next_due_at, next_rule)) = rule.next(due_at) else {
+                break;
+            };
+            dates.push(next_due_at);
+            (due_at, rule) = (next_due_at, next_rule);
+        }
+        dates
+    }
+
+    #[test]
+    fn weekly_by_day_moves_on_within_the_week_and_wraps_by_the_interval() {
+        let every_other_week = rule("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR");
+        // 2024-01-01 is a monday
+        assert_eq!(
+            occurrences(every_other_week, at(2024, 1, 1), 4),
+            [
+                at(2024, 1, 5),
+                at(2024, 1, 15),
+                at(2024, 1, 19),
+                at(2024, 1, 29)
+            ]
+        );
+        // a due date off the rule's days moves on to the next one of them
+        let (next, _) = rule("FREQ=WEEKLY;BYDAY=WE").next(at(2024, 1, 5)).unwrap();
+        assert_eq!(next, at(2024, 1, 10));
+    }
+
+    #[test]
+    fn weekly_without_days_recurs_every_interval_weeks() {
+        let (next, _) = rule("FREQ=WEEKLY;INTERVAL=3").next(at(2024, 1, 3)).unwrap();
+        assert_eq!(next, at(2024, 1, 24));
+    }
+
+    #[test]
+    fn monthly_on_the_31st_skips_short_months() {
+        assert_eq!(
+            occurrences(rule("FREQ=MONTHLY"), at(2024, 1, 31), 4),
+            [
+                at(2024, 3, 31),
+                at(2024, 5, 31),
+                at(2024, 7, 31),
+                at(2024, 8, 31)
+            ]
+        );
+    }
+
+    #[test]
+    fn yearly_on_february_29th_skips_to_the_next_leap_year() {
+        assert_eq!(
+            occurrences(rule("FREQ=YEARLY"), at(2024, 2, 29), 2),
+            [at(2028, 2, 29), at(2032, 2, 29)]
+        );
+    }
+
+    #[test]
+    fn count_ends_the_rule() {
+        let (next, rest) = rule("FREQ=DAILY;COUNT=3").next(at(2024, 1, 1)).unwrap();
+        assert_eq!(next, at(2024, 1, 2));
+        assert_eq!(rest.count, Some(2));
+        assert_eq!(occurrences(rest, next, 10), [at(2024, 1, 3)]);
+        assert_eq!(rule("FREQ=DAILY;COUNT=1").next(at(2024, 1, 1)), None);
+    }
+
+    #[test]
+    fn until_ends_the_rule() {
+        // a date includes the whole day
+        assert_eq!(
+            occurrences(rule("FREQ=DAILY;UNTIL=20240103"), at(2024, 1, 1), 10),
+            [at(2024, 1, 2), at(2024, 1, 3)]
+        );
+        assert_eq!(
+            occurrences(
+                rule("FREQ=DAILY;UNTIL=20240103T090000Z"),
+                at(2024, 1, 1),
+                10
+            ),
+            [at(2024, 1, 2)]
+        );
+    }
+
+    #[test]
+    fn parses_rrule_strings() {
+        assert_eq!(
+            rule("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR;COUNT=5"),
+            Recurrence {
+                frequency: Frequency::Weekly,
+                interval: 2,
+                weekdays: vec![Weekday::Mon, Weekday::Fri],
+                until: None,
+                count: Some(5),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_does_not_support() {
+        for (text, message) in [
+            ("INTERVAL=2", "FREQ is missing"),
+            ("FREQ=HOURLY", "unsupported FREQ \"HOURLY\""),
+            (
+                "FREQ=DAILY;INTERVAL=two",
+                "INTERVAL \"two\" is not a number",
+            ),
+            ("FREQ=DAILY;COUNT=-1", "COUNT \"-1\" is not a number"),
+            ("FREQ=WEEKLY;BYDAY=MO,XX", "unsupported BYDAY value \"XX\""),
+            (
+                "FREQ=DAILY;UNTIL=tomorrow",
+                "UNTIL \"tomorrow\" is neither a date nor a UTC date-time",
+            ),
+            ("FREQ=DAILY;BYMONTH=1", "unsupported part \"BYMONTH\""),
+            ("FREQ", "\"FREQ\" is not of the form KEY=VALUE"),
+        ] {
+            assert_eq!(
+                text.parse::<Recurrence>(),
+                Err(ParseRuleError(message.to_string())),
+                "{}",
+                text
+            );
+        }
+    }
+}
@@ -0,0 +1,25 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A label that its owner can assign to any number of entries of the lists they may edit.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    #[serde(rename = "_id")]
+    pub id: String,
+    /// the user who created the tag, tags from before there were users have none
+    /// until a user claims them with `claim_ownerless`
+    pub owner_id: Option<String>,
+    /// unique among the tags of the owner, ignoring case
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Assigns a tag to an entry.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryTag {
+    pub entry_id: String,
+    pub tag_id: String,
+}
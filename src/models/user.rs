@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Someone who can sign in, only the argon2 hash of their password is stored.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    #[serde(rename = "_id")]
+    pub id: String,
+    /// unique, ignoring case
+    pub name: String,
+    /// in the PHC string format, it includes the salt and the parameters
+    pub password_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A signed in user, requests authenticate with its token as bearer token.
+/// Only a hash of the token is stored, so the data can't be used to sign in.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    /// the hex encoded SHA-256 of the token
+    #[serde(rename = "_id")]
+    pub token_hash: String,
+    pub user_id: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
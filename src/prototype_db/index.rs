@@ -0,0 +1,175 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use super::journal::Operation;
+
+/// Finds the items of a collection by a key without scanning the whole collection.
+/// A unique index also makes sure no two items share a key.
+pub(super) struct Index<T> {
+    pub(super) name: &'static str,
+    unique: bool,
+    key: fn(&T) -> String,
+    /// where the items with the key are in the collection's data, in storage order
+    positions: HashMap<String, BTreeSet<usize>>,
+    /// the key of the item at each position, so a removal can move the later items
+    /// to their new positions without computing their keys again
+    keys: Vec<String>,
+}
+
+/// The positions an operation changes in a collection's data of the length.
+pub(super) enum Change {
+    /// the items at the positions are written, the others stay where they are
+    Written(Vec<usize>),
+    /// the items at the positions are removed, ascending, the later items move up
+    Removed(Vec<usize>),
+}
+
+pub(super) fn change_of<T>(operation: &Operation<T>, length: usize) -> Change {
+    match operation {
+        Operation::Append { .. } => Change::Written(vec![length]),
+        Operation::Replace { index, .. } => Change::Written(vec![*index]),
+        Operation::ReplaceMany { replacements } => {
+            Change::Written(replacements.iter().map(|(index, _)| *index).collect())
+        }
+        Operation::Remove { index } => Change::Removed(vec![*index]),
+        Operation::RemoveMany { indices } => Change::Removed(indices.clone()),
+    }
+}
+
+impl<T> Index<T> {
+    pub(super) fn new(name: &'static str, unique: bool, key: fn(&T) -> String, data: &[T]) -> Self {
+        let mut index = Self {
+            name,
+            unique,
+            key,
+            positions: HashMap::new(),
+            keys: Vec::new(),
+        };
+        index.rebuild(data);
+        index
+    }
+
+    /// Indexes all of the data. Duplicates of a unique key can only exist in files written
+    /// before the index existed, the first one wins just like it does for `find_one`.
+    pub(super) fn rebuild(&mut self, data: &[T]) {
+        self.positions.clear();
+        self.keys.clear();
+        self.insert(data, 0..data.len());
+        if self.unique {
+            let duplicates: usize = self
+                .positions
+                .values()
+                .map(|positions| positions.len() - 1)
+                .sum();
+            if duplicates > 0 {
+                log::warn!(
+                    "{} items share their {} with an earlier item, only the first one can be found",
+                    duplicates,
+                    self.name
+                );
+            }
+        }
+    }
+
+    /// Returns the positions of the items with the key in storage order.
+    pub(super) fn find(&self, key: &str) -> impl Iterator<Item = usize> + '_ {
+        self.positions
+            .get(key)
+            .into_iter()
+            .flat_map(|positions| positions.iter().copied())
+    }
+
+    /// Returns the key that the operation would give to more than one item.
+    pub(super) fn check(&self, operation: &Operation<T>) -> Result<(), String> {
+        if !self.unique {
+            return Ok(());
+        }
+        match operation {
+            Operation::Append { data } => self.check_key((self.key)(data), |_| false),
+            Operation::Replace { index, data } => {
+                self.check_key((self.key)(data), |position| position == *index)
+            }
+            Operation::ReplaceMany { replacements } => {
+                let replaced: HashSet<usize> =
+                    replacements.iter().map(|(index, _)| *index).collect();
+                let mut keys = HashSet::new();
+                for (_, data) in replacements {
+                    let key = (self.key)(data);
+                    if !keys.insert(key.clone()) {
+                        return Err(key);
+                    }
+                    // the replaced items give up their old keys
+                    self.check_key(key, |position| replaced.contains(&position))?;
+                }
+                Ok(())
+            }
+            Operation::Remove { .. } | Operation::RemoveMany { .. } => Ok(()),
+        }
+    }
+
+    fn check_key<F>(&self, key: String, is_replaced: F) -> Result<(), String>
+    where
+        F: Fn(usize) -> bool,
+    {
+        if self.find(&key).any(|position| !is_replaced(position)) {
+            return Err(key);
+        }
+        Ok(())
+    }
+
+    /// Forgets the keys of the items at the positions before they are overwritten.
+    pub(super) fn remove(&mut self, positions: &[usize]) {
+        for &position in positions {
+            let Some(key) = self.keys.get(position) else {
+                continue;
+            };
+            if let Some(key_positions) = self.positions.get_mut(key) {
+                key_positions.remove(&position);
+                if key_positions.is_empty() {
+                    self.positions.remove(key);
+                }
+            }
+        }
+    }
+
+    /// Indexes the items at the positions after they were written.
+    pub(super) fn insert<I>(&mut self, data: &[T], positions: I)
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        for position in positions {
+            let key = (self.key)(&data[position]);
+            if position < self.keys.len() {
+                self.keys[position] = key.clone();
+            } else {
+                self.keys.push(key.clone());
+            }
+            self.positions.entry(key).or_default().insert(position);
+        }
+    }
+
+    /// Forgets the items at the ascending positions and moves the items after the first
+    /// of them up, the way removing them from the collection's data does.
+    pub(super) fn remove_shifting(&mut self, removed: &[usize]) {
+        let Some(&first) = removed.first() else {
+            return;
+        };
+        self.remove(removed);
+        let mut removed = removed.iter().peekable();
+        let mut target = first;
+        for position in first..self.keys.len() {
+            if removed.next_if_eq(&&position).is_some() {
+                continue;
+            }
+            // every position below this one is already taken by the item that ends up there
+            let key_positions = self
+                .positions
+                .get_mut(&self.keys[position])
+                .expect("every item is indexed under its key");
+            key_positions.remove(&position);
+            key_positions.insert(target);
+            self.keys.swap(target, position);
+            target += 1;
+        }
+        self.keys.truncate(target);
+    }
+}
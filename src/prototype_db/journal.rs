@@ -0,0 +1,180 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+
+use serde::{Deserialize, Serialize};
+
+use super::LoadError;
+
+#[derive(Clone, Copy, Debug)]
+pub struct JournalOptions {
+    /// the journal is compacted into the snapshot file once it holds this many operations
+    pub compact_after: usize,
+}
+
+impl Default for JournalOptions {
+    fn default() -> Self {
+        Self {
+            compact_after: 1000,
+        }
+    }
+}
+
+/// A mutation of a collection's data.
+/// Operations refer to positions instead of predicates, so replaying them
+/// against the same snapshot always produces the same result.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub(super) enum Operation<T> {
+    Append { data: T },
+    Replace { index: usize, data: T },
+    ReplaceMany { replacements: Vec<(usize, T)> },
+    Remove { index: usize },
+    RemoveMany { indices: Vec<usize> },
+}
+
+impl<T> Operation<T> {
+    /// Whether the positions the operation refers to exist in data of the length,
+    /// which only a corrupt journal gets wrong.
+    fn fits(&self, len: usize) -> bool {
+        match self {
+            Operation::Append { .. } => true,
+            Operation::Replace { index, .. } | Operation::Remove { index } => *index < len,
+            Operation::ReplaceMany { replacements } => {
+                replacements.iter().all(|(index, _)| *index < len)
+            }
+            Operation::RemoveMany { indices } => {
+                indices.windows(2).all(|pair| pair[0] < pair[1])
+                    && indices.last().is_none_or(|index| *index < len)
+            }
+        }
+    }
+
+    pub(super) fn apply(self, data: &mut Vec<T>) {
+        match self {
+            Operation::Append { data: new_data } => data.push(new_data),
+            Operation::Replace {
+                index,
+                data: new_data,
+            } => data[index] = new_data,
+            Operation::ReplaceMany { replacements } => {
+                for (index, new_data) in replacements {
+                    data[index] = new_data;
+                }
+            }
+            Operation::Remove { index } => {
+                data.remove(index);
+            }
+            Operation::RemoveMany { indices } => {
+                // indices are ascending, removing from the back keeps the others valid
+                for index in indices.into_iter().rev() {
+                    data.remove(index);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Record<O> {
+    sequence: u64,
+    operation: O,
+}
+
+/// An append-only log of operations, one json record per line.
+pub(super) struct Journal {
+    file: fs::File,
+}
+
+impl Journal {
+    /// Opens the journal and discards whatever it contains,
+    /// the caller has to `replay` it into the snapshot beforehand.
+    pub(super) fn open(filename: &str) -> Result<Self, io::Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(filename)?;
+        let mut journal = Self { file };
+        journal.truncate()?;
+        Ok(journal)
+    }
+
+    /// Appends the records and syncs the journal once for all of them.
+    pub(super) fn append<'o, T, I>(&mut self, records: I) -> Result<(), io::Error>
+    where
+        T: Serialize + 'o,
+        I: IntoIterator<Item = (u64, &'o Operation<T>)>,
+    {
+        let mut lines = String::new();
+        for (sequence, operation) in records {
+            lines.push_str(&serde_json::to_string(&Record {
+                sequence,
+                operation,
+            })?);
+            lines.push('\n');
+        }
+        self.file.write_all(lines.as_bytes())?;
+        self.file.sync_data()
+    }
+
+    pub(super) fn truncate(&mut self) -> Result<(), io::Error> {
+        self.file.set_len(0)?;
+        self.file.sync_all()
+    }
+}
+
+/// Applies all operations of the journal file that are newer than `sequence` to `data`
+/// and returns the sequence number of the last applied operation.
+/// A torn last line, as left behind by a crash while appending, is ignored.
+pub(super) fn replay<T>(filename: &str, sequence: u64, data: &mut Vec<T>) -> Result<u64, LoadError>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let file = match fs::File::open(filename) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(sequence),
+        Err(e) => return Err(LoadError::io(filename, e)),
+    };
+    let lines = BufReader::new(file)
+        .lines()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| LoadError::io(filename, e))?;
+
+    let mut sequence = sequence;
+    for (line_index, line) in lines.iter().enumerate() {
+        let record: Record<Operation<T>> = match serde_json::from_str(line) {
+            Ok(record) => record,
+            Err(_) if line_index == lines.len() - 1 => {
+                log::warn!("ignoring incomplete last record of {}", filename);
+                break;
+            }
+            Err(error) => {
+                return Err(LoadError::Corrupt {
+                    filename: filename.to_string(),
+                    error,
+                })
+            }
+        };
+        if record.sequence <= sequence {
+            // already part of the snapshot
+            continue;
+        }
+        if record.sequence != sequence + 1 {
+            log::warn!(
+                "{} continues at operation {} but the snapshot ends at {}, dropping the rest",
+                filename,
+                record.sequence,
+                sequence
+            );
+            break;
+        }
+        if !record.operation.fits(data.len()) {
+            return Err(LoadError::InvalidOperation {
+                filename: filename.to_string(),
+                sequence: record.sequence,
+            });
+        }
+        record.operation.apply(data);
+        sequence = record.sequence;
+    }
+    Ok(sequence)
+}
@@ -1,208 +1,843 @@
 use std::io::{Read, Write};
 use std::path::Path;
-use std::sync::{Arc, Mutex};
-use std::{fs, io};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::{fmt, fs, io};
 
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
+use index::{Change, Index};
+pub use journal::JournalOptions;
+use journal::{Journal, Operation};
+use persistence::{PendingWrite, Persistence};
+use transaction::Staged;
+pub use transaction::Transaction;
+
+mod index;
+mod journal;
+mod persistence;
+mod transaction;
+
+/// Name of the unique index every collection of models has on their `_id`.
+pub const ID_INDEX: &str = "_id";
+/// Name of the index of the entry and membership collections on the list they belong to.
+pub const LIST_ID_INDEX: &str = "listId";
+/// Name of the index of the list and tag collections on the user who owns them.
+pub const OWNER_ID_INDEX: &str = "ownerId";
+/// Name of the index of the membership collection on the user a membership is for.
+pub const USER_ID_INDEX: &str = "userId";
+/// Name of the unique index of the user collection on the lowercased name
+/// and of the tag collection on `tag_name_key`.
+pub const NAME_INDEX: &str = "name";
+
+/// Tag names only have to be unique among the tags of the same owner, ignoring case.
+pub fn tag_name_key(owner_id: Option<&str>, name: &str) -> String {
+    format!("{}/{}", owner_id.unwrap_or_default(), name.to_lowercase())
+}
+
 pub struct Database {
-    dir: String,
-    // TODO does a RwLock make more sense?
-    list_collection: Arc<Mutex<Collection<crate::models::list::List>>>,
-    entry_collection: Arc<Mutex<Collection<crate::models::entry::Entry>>>,
+    list_collection: SharedCollection<crate::models::list::List>,
+    entry_collection: SharedCollection<crate::models::entry::Entry>,
+    tag_collection: SharedCollection<crate::models::tag::Tag>,
+    entry_tag_collection: SharedCollection<crate::models::tag::EntryTag>,
+    membership_collection: SharedCollection<crate::models::membership::Membership>,
+    user_collection: SharedCollection<crate::models::user::User>,
+    session_collection: SharedCollection<crate::models::user::Session>,
+}
+
+/// Why a collection file could not be loaded.
+/// A missing file is not an error, `Collection::new` creates an empty one instead.
+#[derive(Debug)]
+pub enum LoadError {
+    /// the file exists but does not contain a valid collection
+    Corrupt {
+        filename: String,
+        error: serde_json::Error,
+    },
+    /// an operation of the journal refers to a position the data does not have
+    InvalidOperation {
+        filename: String,
+        sequence: u64,
+    },
+    Io {
+        filename: String,
+        error: io::Error,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Corrupt { filename, error } => {
+                write!(f, "{} is corrupt: {}", filename, error)
+            }
+            LoadError::InvalidOperation { filename, sequence } => write!(
+                f,
+                "{} is corrupt: operation {} refers to a position the data does not have",
+                filename, sequence
+            ),
+            LoadError::Io { filename, error } => {
+                write!(f, "could not access {}: {}", filename, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl LoadError {
+    fn io(filename: &str, error: io::Error) -> Self {
+        LoadError::Io {
+            filename: filename.to_string(),
+            error,
+        }
+    }
+
+    fn is_not_found(&self) -> bool {
+        matches!(self, LoadError::Io { error, .. } if error.kind() == io::ErrorKind::NotFound)
+    }
+}
+
+/// Why a mutation of a collection failed.
+#[derive(Debug)]
+pub enum WriteError {
+    /// the data was changed but could not be persisted,
+    /// it is written along with the next change of the collection
+    Io(io::Error),
+    /// the data would share the key of a unique index with data that is already there
+    Duplicate {
+        collection: &'static str,
+        index: &'static str,
+        key: String,
+    },
+    /// the data refers to data with the id that is not there (anymore)
+    Missing {
+        collection: &'static str,
+        id: String,
+    },
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Io(e) => write!(f, "{}", e),
+            WriteError::Duplicate {
+                collection,
+                index,
+                key,
+            } => write!(
+                f,
+                "{} with {} \"{}\" already exists",
+                collection, index, key
+            ),
+            WriteError::Missing { collection, id } => {
+                write!(f, "{} \"{}\" does not exist", collection, id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for WriteError {}
+
+impl From<io::Error> for WriteError {
+    fn from(e: io::Error) -> Self {
+        WriteError::Io(e)
+    }
 }
 
 impl Database {
-    pub fn new(dir: String) -> Result<Self, io::Error> {
-        let list_collection = Arc::new(Mutex::new(Collection::new("list".to_string(), &dir)?));
-        let entry_collection = Arc::new(Mutex::new(Collection::new("entry".to_string(), &dir)?));
+    /// Opens the database in `dir`, the directory and its collection files
+    /// are created if they don't exist yet.
+    /// Without journal options every mutation rewrites the whole collection file.
+    pub fn new(dir: String, journal_options: Option<JournalOptions>) -> Result<Self, LoadError> {
+        fs::create_dir_all(&dir).map_err(|e| LoadError::io(&dir, e))?;
+        let list_collection = SharedCollection::new(
+            Collection::new("list", &dir, journal_options)?
+                .with_unique_index(ID_INDEX, |model: &crate::models::list::List| {
+                    model.id.clone()
+                })
+                // lists without an owner are indexed under the empty string, which is no user's id
+                .with_index(OWNER_ID_INDEX, |model: &crate::models::list::List| {
+                    model.owner_id.clone().unwrap_or_default()
+                }),
+        );
+        let entry_collection = SharedCollection::new(
+            Collection::new("entry", &dir, journal_options)?
+                .with_unique_index(ID_INDEX, |model: &crate::models::entry::Entry| {
+                    model.id.clone()
+                })
+                .with_index(LIST_ID_INDEX, |model: &crate::models::entry::Entry| {
+                    model.list_id.clone()
+                }),
+        );
+        let tag_collection = SharedCollection::new(
+            Collection::new("tag", &dir, journal_options)?
+                .with_unique_index(ID_INDEX, |model: &crate::models::tag::Tag| model.id.clone())
+                .with_index(OWNER_ID_INDEX, |model: &crate::models::tag::Tag| {
+                    model.owner_id.clone().unwrap_or_default()
+                })
+                .with_unique_index(NAME_INDEX, |model: &crate::models::tag::Tag| {
+                    tag_name_key(model.owner_id.as_deref(), &model.name)
+                }),
+        );
+        let entry_tag_collection =
+            SharedCollection::new(Collection::new("entry_tag", &dir, journal_options)?);
+        let membership_collection = SharedCollection::new(
+            Collection::new("membership", &dir, journal_options)?
+                .with_index(
+                    LIST_ID_INDEX,
+                    |model: &crate::models::membership::Membership| model.list_id.clone(),
+                )
+                .with_index(
+                    USER_ID_INDEX,
+                    |model: &crate::models::membership::Membership| model.user_id.clone(),
+                ),
+        );
+        let user_collection = SharedCollection::new(
+            Collection::new("user", &dir, journal_options)?
+                .with_unique_index(ID_INDEX, |model: &crate::models::user::User| {
+                    model.id.clone()
+                })
+                .with_unique_index(NAME_INDEX, |model: &crate::models::user::User| {
+                    model.name.to_lowercase()
+                }),
+        );
+        let session_collection = SharedCollection::new(
+            Collection::new("session", &dir, journal_options)?
+                .with_unique_index(ID_INDEX, |model: &crate::models::user::Session| {
+                    model.token_hash.clone()
+                }),
+        );
         Ok(Self {
-            dir,
             list_collection,
             entry_collection,
+            tag_collection,
+            entry_tag_collection,
+            membership_collection,
+            user_collection,
+            session_collection,
         })
     }
 
-    pub fn get_list_collection(&self) -> Arc<Mutex<Collection<crate::models::list::List>>> {
-        self.list_collection.clone()
+    pub fn get_list_collection(&self) -> &SharedCollection<crate::models::list::List> {
+        &self.list_collection
+    }
+
+    pub fn get_entry_collection(&self) -> &SharedCollection<crate::models::entry::Entry> {
+        &self.entry_collection
+    }
+
+    pub fn get_tag_collection(&self) -> &SharedCollection<crate::models::tag::Tag> {
+        &self.tag_collection
+    }
+
+    pub fn get_entry_tag_collection(&self) -> &SharedCollection<crate::models::tag::EntryTag> {
+        &self.entry_tag_collection
     }
 
-    pub fn get_entry_collection(&self) -> Arc<Mutex<Collection<crate::models::entry::Entry>>> {
-        self.entry_collection.clone()
+    pub fn get_membership_collection(
+        &self,
+    ) -> &SharedCollection<crate::models::membership::Membership> {
+        &self.membership_collection
     }
+
+    pub fn get_user_collection(&self) -> &SharedCollection<crate::models::user::User> {
+        &self.user_collection
+    }
+
+    pub fn get_session_collection(&self) -> &SharedCollection<crate::models::user::Session> {
+        &self.session_collection
+    }
+}
+
+/// A collection that is shared between threads. Any number of them can read it at once,
+/// while a write locks it exclusively. The files are only written once the write
+/// has released the lock, so readers never wait for the disk.
+pub struct SharedCollection<T> {
+    collection: RwLock<Collection<T>>,
 }
 
-fn read_data<T>(filename: &str) -> Result<T, io::Error>
+impl<T> SharedCollection<T>
+where
+    T: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    fn new(mut collection: Collection<T>) -> Self {
+        collection.defer_writes = true;
+        Self {
+            collection: RwLock::new(collection),
+        }
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, Collection<T>> {
+        if self.collection.is_poisoned() {
+            drop(self.lock());
+        }
+        self.collection
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Runs `f` with the collection locked for writing and persists what it changed
+    /// after unlocking it. The changes are kept even if `f` fails part of the way through.
+    pub fn write<F, R, E>(&self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut Collection<T>) -> Result<R, E>,
+        E: From<WriteError>,
+    {
+        let (result, pending_writes, persistence) = {
+            let mut collection = self.lock();
+            let result = f(&mut collection);
+            let pending_writes = std::mem::take(&mut collection.pending_writes);
+            (result, pending_writes, collection.persistence.clone())
+        };
+        // everything is handed over before waiting, so the writer can coalesce it
+        let acknowledgements: Vec<_> = pending_writes
+            .into_iter()
+            .map(|pending_write| persistence.send(pending_write))
+            .collect();
+        let mut persisted = Ok(());
+        for acknowledgement in acknowledgements {
+            let written = acknowledgement.wait();
+            if persisted.is_ok() {
+                persisted = written;
+            }
+        }
+        let value = result?;
+        persisted?;
+        Ok(value)
+    }
+
+    /// Locks the collection for writing. If a write panicked while holding the lock,
+    /// what it left behind is undone first instead of failing every later call.
+    fn lock(&self) -> RwLockWriteGuard<'_, Collection<T>> {
+        let mut collection = self
+            .collection
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        if self.collection.is_poisoned() {
+            collection.recover();
+            self.collection.clear_poison();
+        }
+        collection
+    }
+}
+
+fn backup_filename(filename: &str) -> String {
+    format!("{}.bak", filename)
+}
+
+fn temp_filename(filename: &str) -> String {
+    format!("{}.tmp", filename)
+}
+
+fn read_file<T>(filename: &str) -> Result<T, LoadError>
 where
     T: serde::de::DeserializeOwned,
 {
     let path = Path::new(filename);
-    let mut file = fs::File::open(&path)?;
+    let mut file = fs::File::open(path).map_err(|e| LoadError::io(filename, e))?;
     let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-    let data: T = serde_json::from_str(&contents)?;
-    Ok(data)
+    file.read_to_string(&mut contents)
+        .map_err(|e| LoadError::io(filename, e))?;
+    serde_json::from_str(&contents).map_err(|error| LoadError::Corrupt {
+        filename: filename.to_string(),
+        error,
+    })
+}
+
+/// Reads the file and falls back to its `.bak` copy if the file itself
+/// is missing or cannot be parsed, e.g. after a crash in the middle of `write_data`.
+/// The error of the primary file is returned if the backup cannot be read either.
+fn read_data<T>(filename: &str) -> Result<T, LoadError>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let primary_error = match read_file(filename) {
+        Ok(data) => return Ok(data),
+        Err(e) => e,
+    };
+    let backup_filename = backup_filename(filename);
+    match read_file(&backup_filename) {
+        Ok(data) => {
+            log::warn!(
+                "could not read {} ({}), recovered from {}",
+                filename,
+                primary_error,
+                backup_filename
+            );
+            Ok(data)
+        }
+        Err(_) => Err(primary_error),
+    }
 }
 
+/// Writes the data to a temporary file, syncs it to disk and then renames it
+/// over the original file, so a crash never leaves a partially written file behind.
+/// The previous version of the file is kept as `.bak`.
 fn write_data<T>(filename: &str, data: &T) -> Result<(), io::Error>
 where
     T: serde::Serialize,
 {
     let path = Path::new(filename);
-    let mut file = fs::File::create(&path)?;
+    let temp_filename = temp_filename(filename);
     let serialized_data = serde_json::to_string_pretty(data)?;
+
+    let mut file = fs::File::create(&temp_filename)?;
     file.write_all(serialized_data.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    // if the process dies between these two renames there is no primary file,
+    // read_data then recovers from the backup
+    if path.exists() {
+        fs::rename(path, backup_filename(filename))?;
+    }
+    fs::rename(&temp_filename, path)?;
+    sync_parent_directory(path)
+}
+
+/// Makes the renames durable, a rename is only persisted once the directory is synced.
+#[cfg(unix)]
+fn sync_parent_directory(path: &Path) -> Result<(), io::Error> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::File::open(parent)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_parent_directory(_path: &Path) -> Result<(), io::Error> {
     Ok(())
 }
 
+/// The content of a collection file, `D` is the data as it is read or written.
 #[derive(Clone, Serialize, Deserialize)]
-struct DataContainer<T> {
+struct DataContainer<D> {
     count: usize,
-    data: Vec<T>,
+    /// number of the last operation that is part of this snapshot,
+    /// journal records up to this number have already been applied
+    #[serde(default)]
+    sequence: u64,
+    data: D,
 }
 
-#[derive(Clone)]
 pub struct Collection<T> {
-    name: String,
-    directory: String,
-    data_container: DataContainer<T>,
+    name: &'static str,
+    /// shared with the snapshots that are being written,
+    /// it is copied when it changes while a snapshot still holds it
+    data: Arc<Vec<T>>,
+    /// number of the last operation that was applied to the data
+    sequence: u64,
+    journal_options: Option<JournalOptions>,
+    /// number of operations written to the journal since the last snapshot
+    journaled: usize,
+    persistence: Arc<Persistence<T>>,
+    /// set for shared collections, which persist their changes once they are unlocked
+    defer_writes: bool,
+    pending_writes: Vec<PendingWrite<T>>,
+    indexes: Vec<Index<T>>,
+    /// set while a transaction is running
+    staged: Option<Staged<T>>,
 }
 
 impl<T> Collection<T>
 where
-    T: Clone + Serialize + DeserializeOwned,
+    T: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
 {
-    pub fn new(name: String, directory: &str) -> Result<Self, io::Error> {
-        let base_path = Path::new(directory).join(&name);
+    pub fn new(
+        name: &'static str,
+        directory: &str,
+        journal_options: Option<JournalOptions>,
+    ) -> Result<Self, LoadError> {
+        let base_path = Path::new(directory).join(name);
         let filename = format!("{}.json", base_path.display());
+        let journal_filename = format!("{}.journal", base_path.display());
+
+        let mut data_container: DataContainer<Vec<T>> = match read_data(&filename) {
+            Ok(data_container) => data_container,
+            Err(e) if e.is_not_found() => {
+                let data_container = DataContainer {
+                    count: 0,
+                    sequence: 0,
+                    data: Vec::new(),
+                };
+                write_data(&filename, &data_container).map_err(|e| LoadError::io(&filename, e))?;
+                log::info!("created empty collection file {}", filename);
+                data_container
+            }
+            Err(e) => return Err(e),
+        };
 
-        let data_container = read_data(&filename)?;
+        // the journal is replayed even if journaling is turned off now,
+        // otherwise switching modes would lose the operations it holds
+        let sequence = journal::replay(
+            &journal_filename,
+            data_container.sequence,
+            &mut data_container.data,
+        )?;
+        if sequence != data_container.sequence {
+            log::info!(
+                "replayed {} operations from {}",
+                sequence - data_container.sequence,
+                journal_filename
+            );
+            data_container.sequence = sequence;
+            data_container.count = data_container.data.len();
+            write_data(&filename, &data_container).map_err(|e| LoadError::io(&filename, e))?;
+        }
 
+        let journal = match journal_options {
+            Some(_) => Some(
+                Journal::open(&journal_filename)
+                    .map_err(|e| LoadError::io(&journal_filename, e))?,
+            ),
+            None => {
+                match fs::remove_file(&journal_filename) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                        return Err(LoadError::io(&journal_filename, e))
+                    }
+                    _ => {}
+                }
+                None
+            }
+        };
+
+        let persistence = Persistence::new(filename, journal, data_container.sequence);
         Ok(Self {
             name,
-            directory: directory.to_string(),
-            data_container,
+            data: Arc::new(data_container.data),
+            sequence: data_container.sequence,
+            journal_options,
+            journaled: 0,
+            persistence: Arc::new(persistence),
+            defer_writes: false,
+            pending_writes: Vec::new(),
+            indexes: Vec::new(),
+            staged: None,
         })
     }
 
-    fn get_filename(&self) -> String {
-        let base_path = Path::new(&self.directory).join(&self.name);
-        format!("{}.json", base_path.display())
+    /// Adds a unique index on the key `key` returns, mutations that would give
+    /// two items the same key fail with `WriteError::Duplicate`.
+    pub fn with_unique_index(mut self, name: &'static str, key: fn(&T) -> String) -> Self {
+        let index = Index::new(name, true, key, &self.data);
+        self.indexes.push(index);
+        self
     }
 
-    fn save(&mut self) -> Result<(), io::Error> {
-        let filename = self.get_filename();
-        self.data_container.count = self.data_container.data.len();
-        write_data(&filename, &self.data_container)
+    /// Adds an index on the key `key` returns, which any number of items may share.
+    pub fn with_index(mut self, name: &'static str, key: fn(&T) -> String) -> Self {
+        let index = Index::new(name, false, key, &self.data);
+        self.indexes.push(index);
+        self
     }
 
-    pub fn find_one<F>(&self, predicate: F) -> Option<&T>
+    /// What has to be written for the operations that were just applied,
+    /// the last of them has the current sequence number.
+    /// The journal is replaced by a snapshot once it holds enough operations.
+    fn pending_write(&mut self, operations: Vec<Operation<T>>) -> PendingWrite<T> {
+        if let Some(options) = self.journal_options {
+            self.journaled += operations.len();
+            if self.journaled < options.compact_after && !self.persistence.needs_snapshot() {
+                return PendingWrite::Journal {
+                    first: self.sequence + 1 - operations.len() as u64,
+                    operations,
+                };
+            }
+        }
+        self.journaled = 0;
+        PendingWrite::Snapshot {
+            sequence: self.sequence,
+            data: self.data.clone(),
+        }
+    }
+
+    /// Writes the pending write right away, unless the collection is shared,
+    /// then `SharedCollection::write` writes it once the collection is unlocked.
+    fn persist(&mut self, pending_write: PendingWrite<T>) -> Result<(), WriteError> {
+        if self.defer_writes {
+            self.pending_writes.push(pending_write);
+            return Ok(());
+        }
+        self.persistence.write(pending_write)
+    }
+
+    /// Applies the operation and persists it, either by appending it to the journal
+    /// or, without a journal, by rewriting the whole file.
+    /// Nothing is changed if the operation violates a unique index, but the operation
+    /// stays applied if it can't be persisted.
+    fn commit(&mut self, operation: Operation<T>) -> Result<(), WriteError> {
+        for index in &self.indexes {
+            index
+                .check(&operation)
+                .map_err(|key| WriteError::Duplicate {
+                    collection: self.name,
+                    index: index.name,
+                    key,
+                })?;
+        }
+        // a transaction persists its operations once it is done
+        let Some(operation) = self.stage(operation) else {
+            return Ok(());
+        };
+        self.sequence += 1;
+        let journaled = match self.journal_options {
+            Some(_) => vec![operation.clone()],
+            None => Vec::new(),
+        };
+        self.apply(operation);
+        let pending_write = self.pending_write(journaled);
+        self.persist(pending_write)
+    }
+
+    /// Applies the operation to the data and keeps the indexes up to date.
+    fn apply(&mut self, operation: Operation<T>) {
+        let data = Arc::make_mut(&mut self.data);
+        match index::change_of(&operation, data.len()) {
+            Change::Written(positions) => {
+                for index in &mut self.indexes {
+                    index.remove(&positions);
+                }
+                operation.apply(data);
+                for index in &mut self.indexes {
+                    index.insert(data, positions.iter().copied());
+                }
+            }
+            Change::Removed(positions) => {
+                for index in &mut self.indexes {
+                    index.remove_shifting(&positions);
+                }
+                operation.apply(data);
+            }
+        }
+    }
+
+    fn index(&self, name: &str) -> &Index<T> {
+        self.indexes
+            .iter()
+            .find(|index| index.name == name)
+            .unwrap_or_else(|| panic!("{} has no index {}", self.name, name))
+    }
+
+    /// Returns the positions of the items with any of the keys in storage order.
+    fn positions_of_keys<'k, I>(&self, index: &str, keys: I) -> Vec<usize>
     where
-        F: Fn(&T) -> bool,
+        I: IntoIterator<Item = &'k str>,
     {
-        self.data_container
-            .data
-            .iter()
-            .find(|&data| predicate(data))
+        let index = self.index(index);
+        let mut positions: Vec<usize> = keys.into_iter().flat_map(|key| index.find(key)).collect();
+        positions.sort_unstable();
+        positions.dedup();
+        positions
     }
 
-    pub fn find<F>(&self, predicate: F) -> Vec<&T>
+    fn delete_at(&mut self, indices: Vec<usize>) -> Result<usize, WriteError> {
+        let deleted_counter = indices.len();
+        if deleted_counter > 0 {
+            self.commit(Operation::RemoveMany { indices })?;
+        }
+        Ok(deleted_counter)
+    }
+
+    /// Applies `update_fn` to the data at the ascending `indices` and returns the updated data.
+    fn patch_at<G>(&mut self, indices: Vec<usize>, mut update_fn: G) -> Result<Vec<T>, WriteError>
+    where
+        G: FnMut(&mut T),
+    {
+        let replacements: Vec<(usize, T)> = indices
+            .into_iter()
+            .map(|index| {
+                let mut data = self.data[index].clone();
+                update_fn(&mut data);
+                (index, data)
+            })
+            .collect();
+        let patched = replacements.iter().map(|(_, data)| data.clone()).collect();
+        if !replacements.is_empty() {
+            self.commit(Operation::ReplaceMany { replacements })?;
+        }
+        Ok(patched)
+    }
+
+    fn put_at(&mut self, index: Option<usize>, data: T) -> Result<T, WriteError> {
+        let operation = match index {
+            Some(index) => Operation::Replace {
+                index,
+                data: data.clone(),
+            },
+            None => Operation::Append { data: data.clone() },
+        };
+        self.commit(operation)?;
+        Ok(data)
+    }
+
+    fn matching_positions<F>(&self, predicate: F) -> Vec<usize>
     where
         F: Fn(&T) -> bool,
     {
-        self.data_container
-            .data
+        self.data
             .iter()
-            .filter(|&data| predicate(data))
+            .enumerate()
+            .filter(|(_, data)| predicate(data))
+            .map(|(index, _)| index)
             .collect()
     }
 
-    pub fn get_all(&self) -> &[T] {
-        &self.data_container.data
+    pub fn find_one<F>(&self, predicate: F) -> Option<&T>
+    where
+        F: Fn(&T) -> bool,
+    {
+        self.data.iter().find(|&data| predicate(data))
     }
 
-    pub fn append(&mut self, data: T) -> Result<(), io::Error> {
-        self.data_container.count += 1;
-        self.data_container.data.push(data);
-        self.save()
+    /// Looks the item up in the index with the given name,
+    /// the first one in storage order if several items share the key.
+    pub fn find_one_by_key(&self, index: &str, key: &str) -> Option<&T> {
+        self.index(index)
+            .find(key)
+            .next()
+            .map(|position| &self.data[position])
     }
 
-    pub fn delete_one<F>(&mut self, predicate: F) -> Result<Option<T>, io::Error>
+    pub fn find<F>(&self, predicate: F) -> Vec<&T>
     where
         F: Fn(&T) -> bool,
     {
-        if let Some(index) = self
-            .data_container
-            .data
-            .iter()
-            .position(|data| predicate(data))
-        {
-            let data = self.data_container.data.remove(index);
-            self.save()?;
-            Ok(Some(data))
-        } else {
-            Ok(None)
-        }
+        self.data.iter().filter(|&data| predicate(data)).collect()
     }
 
-    pub fn delete_many<F>(&mut self, predicate: F) -> Result<usize, io::Error>
+    /// Returns the items with the key in the index with the given name, in storage order.
+    pub fn find_by_key(&self, index: &str, key: &str) -> Vec<&T> {
+        self.index(index)
+            .find(key)
+            .map(|position| &self.data[position])
+            .collect()
+    }
+
+    pub fn append(&mut self, data: T) -> Result<(), WriteError> {
+        self.commit(Operation::Append { data })
+    }
+
+    pub fn delete_one<F>(&mut self, predicate: F) -> Result<Option<T>, WriteError>
     where
         F: Fn(&T) -> bool,
     {
-        let mut deleted_counter = 0;
-        let retain_predicate = |data: &T| {
-            if predicate(data) {
-                deleted_counter += 1;
-                false
-            } else {
-                true
-            }
+        let index = self.data.iter().position(predicate);
+        self.delete_one_at(index)
+    }
+
+    pub fn delete_one_by_key(&mut self, index: &str, key: &str) -> Result<Option<T>, WriteError> {
+        let index = self.index(index).find(key).next();
+        self.delete_one_at(index)
+    }
+
+    fn delete_one_at(&mut self, index: Option<usize>) -> Result<Option<T>, WriteError> {
+        let Some(index) = index else {
+            return Ok(None);
         };
-        self.data_container.data.retain(retain_predicate);
-        if deleted_counter > 0 {
-            self.save()?;
-        }
-        Ok(deleted_counter)
+        let data = self.data[index].clone();
+        self.commit(Operation::Remove { index })?;
+        Ok(Some(data))
+    }
+
+    pub fn delete_many<F>(&mut self, predicate: F) -> Result<usize, WriteError>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let indices = self.matching_positions(predicate);
+        self.delete_at(indices)
+    }
+
+    /// Deletes the data with any of the keys in the index with the given name.
+    pub fn delete_many_by_keys<'k, I>(&mut self, index: &str, keys: I) -> Result<usize, WriteError>
+    where
+        I: IntoIterator<Item = &'k str>,
+    {
+        let indices = self.positions_of_keys(index, keys);
+        self.delete_at(indices)
     }
 
-    pub fn patch_one<F, G>(&mut self, predicate: F, update_fn: G) -> Result<Option<T>, io::Error>
+    pub fn patch_one<F, G>(&mut self, predicate: F, update_fn: G) -> Result<Option<T>, WriteError>
     where
         F: Fn(&T) -> bool,
         G: FnOnce(&mut T),
     {
-        let mut should_save = false;
-        let value = if let Some(data) = self
-            .data_container
-            .data
-            .iter_mut()
-            .find(|data| predicate(data))
-        {
-            update_fn(data);
-            should_save = true;
-            Ok(Some(data.clone()))
-        } else {
-            Ok(None)
+        let index = self.data.iter().position(predicate);
+        self.patch_one_at(index, update_fn)
+    }
+
+    pub fn patch_one_by_key<G>(
+        &mut self,
+        index: &str,
+        key: &str,
+        update_fn: G,
+    ) -> Result<Option<T>, WriteError>
+    where
+        G: FnOnce(&mut T),
+    {
+        let index = self.index(index).find(key).next();
+        self.patch_one_at(index, update_fn)
+    }
+
+    fn patch_one_at<G>(
+        &mut self,
+        index: Option<usize>,
+        update_fn: G,
+    ) -> Result<Option<T>, WriteError>
+    where
+        G: FnOnce(&mut T),
+    {
+        let Some(index) = index else {
+            return Ok(None);
         };
-        if should_save {
-            self.save()?;
-        }
-        value
+        let mut data = self.data[index].clone();
+        update_fn(&mut data);
+        self.commit(Operation::Replace {
+            index,
+            data: data.clone(),
+        })?;
+        Ok(Some(data))
     }
 
-    pub fn put_one<F>(&mut self, predicate: F, data: T) -> Result<T, io::Error>
+    /// Applies `update_fn` to all matching data and returns the updated data.
+    pub fn patch_many<F, G>(&mut self, predicate: F, update_fn: G) -> Result<Vec<T>, WriteError>
     where
         F: Fn(&T) -> bool,
+        G: FnMut(&mut T),
     {
-        if let Some(index) = self.data_container.data.iter().position(|d| predicate(d)) {
-            let _ = self.data_container.data.remove(index);
-        }
-        self.data_container.data.push(data.clone());
-        self.save()?;
-        Ok(data)
+        let indices = self.matching_positions(predicate);
+        self.patch_at(indices, update_fn)
+    }
+
+    /// Applies `update_fn` to the data with any of the keys in the index with the given name
+    /// and returns the updated data.
+    pub fn patch_many_by_keys<'k, I, G>(
+        &mut self,
+        index: &str,
+        keys: I,
+        update_fn: G,
+    ) -> Result<Vec<T>, WriteError>
+    where
+        I: IntoIterator<Item = &'k str>,
+        G: FnMut(&mut T),
+    {
+        let indices = self.positions_of_keys(index, keys);
+        self.patch_at(indices, update_fn)
+    }
+
+    /// Replaces the matching data where it is or appends the data if nothing matches.
+    pub fn put_one<F>(&mut self, predicate: F, data: T) -> Result<T, WriteError>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let index = self.data.iter().position(predicate);
+        self.put_at(index, data)
     }
 
-    pub fn count(&self) -> usize {
-        self.data_container.data.len()
+    /// Like `put_one`, with the data to replace looked up in the index with the given name.
+    pub fn put_one_by_key(&mut self, index: &str, key: &str, data: T) -> Result<T, WriteError> {
+        let index = self.index(index).find(key).next();
+        self.put_at(index, data)
     }
 }
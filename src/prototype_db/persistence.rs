@@ -0,0 +1,252 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use serde::Serialize;
+
+use super::{journal::Operation, write_data, DataContainer, Journal, WriteError};
+
+/// What has to be written to disk for operations a collection has applied to its data.
+pub(super) enum PendingWrite<T> {
+    /// operations to append to the journal, the first one has the sequence number `first`
+    Journal {
+        first: u64,
+        operations: Vec<Operation<T>>,
+    },
+    /// the whole data as of the operation `sequence`, it replaces the journal
+    Snapshot { sequence: u64, data: Arc<Vec<T>> },
+}
+
+impl<T> PendingWrite<T> {
+    fn last_sequence(&self) -> u64 {
+        match self {
+            PendingWrite::Journal { first, operations } => first + operations.len() as u64 - 1,
+            PendingWrite::Snapshot { sequence, .. } => *sequence,
+        }
+    }
+}
+
+/// The files of a collection, written by a thread of their own.
+/// Writes are handed over once the collection is unlocked, so they can arrive out of order,
+/// the writer puts them back into the order of their sequence numbers.
+pub(super) struct Persistence<T> {
+    sender: Option<mpsc::Sender<Request<T>>>,
+    writer: Option<thread::JoinHandle<()>>,
+    /// a write failed, so the journal has a gap that only a snapshot can close
+    needs_snapshot: Arc<AtomicBool>,
+}
+
+struct Request<T> {
+    pending_write: PendingWrite<T>,
+    done: mpsc::SyncSender<Result<(), io::Error>>,
+}
+
+/// Tells whether a pending write made it to disk.
+pub(super) struct Acknowledgement(mpsc::Receiver<Result<(), io::Error>>);
+
+impl Acknowledgement {
+    /// Blocks until the pending write and everything before it is durable.
+    pub(super) fn wait(self) -> Result<(), WriteError> {
+        match self.0.recv() {
+            Ok(result) => Ok(result?),
+            Err(_) => Err(io::Error::other("the writer of the collection has stopped").into()),
+        }
+    }
+}
+
+impl<T> Persistence<T>
+where
+    T: Serialize + Send + Sync + 'static,
+{
+    pub(super) fn new(filename: String, journal: Option<Journal>, sequence: u64) -> Self {
+        let needs_snapshot = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+        let writer = Writer {
+            filename: filename.clone(),
+            journal,
+            persisted: sequence,
+            needs_snapshot: needs_snapshot.clone(),
+            waiting: Vec::new(),
+        };
+        let writer = thread::Builder::new()
+            .name(format!("writer of {}", filename))
+            .spawn(move || writer.run(receiver))
+            .expect("could not start the writer thread");
+        Self {
+            sender: Some(sender),
+            writer: Some(writer),
+            needs_snapshot,
+        }
+    }
+}
+
+impl<T> Persistence<T> {
+    pub(super) fn needs_snapshot(&self) -> bool {
+        self.needs_snapshot.load(Ordering::SeqCst)
+    }
+
+    /// Hands the pending write over to the writer without waiting for it.
+    pub(super) fn send(&self, pending_write: PendingWrite<T>) -> Acknowledgement {
+        let (done, acknowledgement) = mpsc::sync_channel(1);
+        if let Some(sender) = &self.sender {
+            // if the writer is gone, `done` is dropped and waiting reports it
+            let _ = sender.send(Request {
+                pending_write,
+                done,
+            });
+        }
+        Acknowledgement(acknowledgement)
+    }
+
+    pub(super) fn write(&self, pending_write: PendingWrite<T>) -> Result<(), WriteError> {
+        self.send(pending_write).wait()
+    }
+}
+
+impl<T> Drop for Persistence<T> {
+    /// Lets the writer finish what it has been handed, so the files are complete
+    /// once the collection is gone.
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+struct Writer<T> {
+    filename: String,
+    journal: Option<Journal>,
+    /// sequence number of the last operation that was written, or failed to be written
+    persisted: u64,
+    needs_snapshot: Arc<AtomicBool>,
+    /// requests that can't be written before the ones in front of them arrive
+    waiting: Vec<Request<T>>,
+}
+
+impl<T> Writer<T>
+where
+    T: Serialize,
+{
+    /// Writes whatever has arrived in one go, so requests that come in while the disk
+    /// is busy are coalesced: only the newest snapshot is written and all journal records
+    /// are appended with a single sync.
+    fn run(mut self, receiver: mpsc::Receiver<Request<T>>) {
+        while let Ok(request) = receiver.recv() {
+            self.waiting.push(request);
+            self.waiting.extend(receiver.try_iter());
+            self.write_waiting();
+        }
+        if !self.waiting.is_empty() {
+            log::error!(
+                "{} operations for {} were never written, earlier ones are missing",
+                self.waiting.len(),
+                self.filename
+            );
+        }
+    }
+
+    fn write_waiting(&mut self) {
+        self.waiting
+            .sort_by_key(|request| request.pending_write.last_sequence());
+        // the newest snapshot contains everything in front of it
+        let snapshot_position = self
+            .waiting
+            .iter()
+            .rposition(|request| matches!(request.pending_write, PendingWrite::Snapshot { .. }));
+        if let Some(position) = snapshot_position {
+            let requests: Vec<Request<T>> = self.waiting.drain(..=position).collect();
+            let result = match &requests[position].pending_write {
+                PendingWrite::Snapshot { sequence, data } if *sequence > self.persisted => {
+                    self.write_snapshot(*sequence, data)
+                }
+                _ => Ok(()),
+            };
+            self.finish(requests, result);
+        }
+
+        // journal records can only be appended once everything before them is written
+        let mut next = self.persisted + 1;
+        let following = self
+            .waiting
+            .iter()
+            .take_while(|request| match &request.pending_write {
+                PendingWrite::Journal { first, .. } if *first <= next => {
+                    next = next.max(request.pending_write.last_sequence() + 1);
+                    true
+                }
+                _ => false,
+            })
+            .count();
+        if following > 0 {
+            let requests: Vec<Request<T>> = self.waiting.drain(..following).collect();
+            let result = self.append(&requests);
+            self.finish(requests, result);
+        }
+    }
+
+    fn write_snapshot(&mut self, sequence: u64, data: &[T]) -> Result<(), io::Error> {
+        let data_container = DataContainer {
+            count: data.len(),
+            sequence,
+            data,
+        };
+        write_data(&self.filename, &data_container)?;
+        if let Some(journal) = &mut self.journal {
+            journal.truncate()?;
+        }
+        self.needs_snapshot.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn append(&mut self, requests: &[Request<T>]) -> Result<(), io::Error> {
+        if self.needs_snapshot.load(Ordering::SeqCst) {
+            return Err(io::Error::other(
+                "an earlier write failed, the journal can't continue without a snapshot",
+            ));
+        }
+        let Some(journal) = &mut self.journal else {
+            unreachable!("journal writes are only pending for collections with a journal")
+        };
+        let persisted = self.persisted;
+        let records = requests
+            .iter()
+            .filter_map(|request| match &request.pending_write {
+                PendingWrite::Journal { first, operations } if *first > persisted => {
+                    Some((*first, operations))
+                }
+                _ => None,
+            })
+            .flat_map(|(first, operations)| (first..).zip(operations.iter()));
+        journal.append(records)
+    }
+
+    /// Tells the requests how writing them went, even if it failed they are done with,
+    /// the next snapshot writes their operations.
+    fn finish(&mut self, requests: Vec<Request<T>>, result: Result<(), io::Error>) {
+        let last_sequence = requests
+            .iter()
+            .map(|request| request.pending_write.last_sequence())
+            .max()
+            .unwrap_or(self.persisted);
+        if let Err(e) = &result {
+            log::error!(
+                "could not write {} up to operation {}, it is written with the next change: {}",
+                self.filename,
+                last_sequence,
+                e
+            );
+            self.needs_snapshot.store(true, Ordering::SeqCst);
+        }
+        self.persisted = self.persisted.max(last_sequence);
+        for request in requests {
+            let result = match &result {
+                Ok(()) => Ok(()),
+                Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+            };
+            // the caller may not be waiting anymore
+            let _ = request.done.send(result);
+        }
+    }
+}
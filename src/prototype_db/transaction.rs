@@ -0,0 +1,210 @@
+use std::sync::{Arc, RwLockWriteGuard};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::models::{
+    entry::Entry,
+    list::List,
+    membership::Membership,
+    tag::{EntryTag, Tag},
+};
+
+use super::{journal::Operation, persistence::Acknowledgement, Collection, Database, WriteError};
+
+/// All collections of the database, locked for the duration of `Database::transaction`.
+/// Their changes are only persisted once the transaction is done.
+pub struct Transaction<'a> {
+    pub lists: RwLockWriteGuard<'a, Collection<List>>,
+    pub entries: RwLockWriteGuard<'a, Collection<Entry>>,
+    pub tags: RwLockWriteGuard<'a, Collection<Tag>>,
+    pub entry_tags: RwLockWriteGuard<'a, Collection<EntryTag>>,
+    pub memberships: RwLockWriteGuard<'a, Collection<Membership>>,
+}
+
+impl Transaction<'_> {
+    /// The collections in the order they are locked and persisted in.
+    fn collections(&mut self) -> [&mut dyn Staging; 5] {
+        [
+            &mut *self.lists,
+            &mut *self.entries,
+            &mut *self.tags,
+            &mut *self.entry_tags,
+            &mut *self.memberships,
+        ]
+    }
+}
+
+impl Database {
+    /// Runs `f` with all collections locked. They are always locked in the same order,
+    /// so concurrent transactions can't deadlock.
+    /// If `f` fails every collection is rolled back to how it was before, nothing is written.
+    /// Otherwise its changes are persisted after the collections are unlocked, like
+    /// `SharedCollection::write` does: if that fails the changes are kept and written
+    /// with the next change of their collection.
+    pub fn transaction<F, R, E>(&self, f: F) -> Result<R, E>
+    where
+        F: FnOnce(&mut Transaction) -> Result<R, E>,
+        E: From<WriteError>,
+    {
+        let (result, acknowledgements) = {
+            let mut transaction = Transaction {
+                lists: self.list_collection.lock(),
+                entries: self.entry_collection.lock(),
+                tags: self.tag_collection.lock(),
+                entry_tags: self.entry_tag_collection.lock(),
+                memberships: self.membership_collection.lock(),
+            };
+            for collection in transaction.collections() {
+                collection.begin();
+            }
+            let result = f(&mut transaction);
+            let mut acknowledgements = Vec::new();
+            for collection in transaction.collections() {
+                match result {
+                    // handing the writes over doesn't wait for the disk
+                    Ok(_) => acknowledgements.extend(collection.commit()),
+                    Err(_) => collection.rollback(),
+                }
+            }
+            (result, acknowledgements)
+        };
+        let mut persisted = Ok(());
+        for acknowledgement in acknowledgements {
+            let written = acknowledgement.wait();
+            if persisted.is_ok() {
+                persisted = written;
+            }
+        }
+        let value = result?;
+        persisted?;
+        Ok(value)
+    }
+}
+
+/// The changes of a collection that a transaction has not persisted yet.
+pub(super) struct Staged<T> {
+    operations: Vec<Operation<T>>,
+    undo: Vec<Undo<T>>,
+}
+
+/// Reverts an operation that was applied to the data.
+enum Undo<T> {
+    Pop,
+    Replace(Vec<(usize, T)>),
+    /// the removed items, ascending by the position they had
+    Insert(Vec<(usize, T)>),
+}
+
+impl<T> Undo<T>
+where
+    T: Clone,
+{
+    fn of(operation: &Operation<T>, data: &[T]) -> Self {
+        let old = |index: &usize| (*index, data[*index].clone());
+        match operation {
+            Operation::Append { .. } => Undo::Pop,
+            Operation::Replace { index, .. } => Undo::Replace(vec![old(index)]),
+            Operation::ReplaceMany { replacements } => {
+                Undo::Replace(replacements.iter().map(|(index, _)| old(index)).collect())
+            }
+            Operation::Remove { index } => Undo::Insert(vec![old(index)]),
+            Operation::RemoveMany { indices } => Undo::Insert(indices.iter().map(old).collect()),
+        }
+    }
+
+    fn apply(self, data: &mut Vec<T>) {
+        match self {
+            Undo::Pop => {
+                data.pop();
+            }
+            Undo::Replace(old) => {
+                for (index, old_data) in old.into_iter().rev() {
+                    data[index] = old_data;
+                }
+            }
+            Undo::Insert(removed) => {
+                for (index, old_data) in removed {
+                    data.insert(index, old_data);
+                }
+            }
+        }
+    }
+}
+
+impl<T> Collection<T>
+where
+    T: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    /// Applies the operation to the data without persisting it, if a transaction is running.
+    /// Gives the operation back otherwise.
+    pub(super) fn stage(&mut self, operation: Operation<T>) -> Option<Operation<T>> {
+        let Some(staged) = &mut self.staged else {
+            return Some(operation);
+        };
+        staged.undo.push(Undo::of(&operation, &self.data));
+        staged.operations.push(operation.clone());
+        self.apply(operation);
+        None
+    }
+
+    /// Undoes what a write that panicked while holding the lock left behind. A transaction
+    /// it was part of is rolled back, the indexes are rebuilt in case it stopped half way.
+    pub(super) fn recover(&mut self) {
+        log::warn!(
+            "recovering the {} collection after a write panicked",
+            self.name
+        );
+        self.rollback();
+        let data = &*self.data;
+        for index in &mut self.indexes {
+            index.rebuild(data);
+        }
+    }
+}
+
+/// What `Database::transaction` does with each collection, whatever its type.
+trait Staging {
+    fn begin(&mut self);
+    /// Hands the staged operations over to the writer of the collection.
+    fn commit(&mut self) -> Option<Acknowledgement>;
+    fn rollback(&mut self);
+}
+
+impl<T> Staging for Collection<T>
+where
+    T: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+{
+    fn begin(&mut self) {
+        self.staged = Some(Staged {
+            operations: Vec::new(),
+            undo: Vec::new(),
+        });
+    }
+
+    fn commit(&mut self) -> Option<Acknowledgement> {
+        let mut operations = self.staged.take()?.operations;
+        if operations.is_empty() {
+            return None;
+        }
+        self.sequence += operations.len() as u64;
+        if self.journal_options.is_none() {
+            operations.clear();
+        }
+        let pending_write = self.pending_write(operations);
+        Some(self.persistence.send(pending_write))
+    }
+
+    /// Reverts the staged operations, none of them have been persisted.
+    fn rollback(&mut self) {
+        let Some(staged) = self.staged.take() else {
+            return;
+        };
+        let data = Arc::make_mut(&mut self.data);
+        for undo in staged.undo.into_iter().rev() {
+            undo.apply(data);
+        }
+        for index in &mut self.indexes {
+            index.rebuild(data);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::{future::Future, pin::Pin, sync::OnceLock};
+
+use actix_web::{
+    dev::Payload,
+    http::{header, StatusCode},
+    web::{self, ServiceConfig},
+    FromRequest, HttpRequest, HttpResponse, HttpResponseBuilder,
+};
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use chrono::{DateTime, Duration, Utc};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::{
+    models::user::{Session, User},
+    storage::Store,
+};
+
+use super::{
+    blocking,
+    error::ApiError,
+    validation::{Validate, Validator},
+};
+
+/// How long a token stays valid after logging in.
+const SESSION_LIFETIME_DAYS: i64 = 30;
+const TOKEN_BYTES: usize = 32;
+
+/// The user a request is made by, identified by the bearer token in its `Authorization` header.
+/// Handlers that take it as an argument respond with 401 to everyone else.
+pub struct Authenticated {
+    pub user: User,
+    /// identifies the session the request is made with
+    pub token_hash: String,
+}
+
+impl FromRequest for Authenticated {
+    type Error = ApiError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(request: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let token_hash = bearer_token(request).map(hash_token);
+        let db = request
+            .app_data::<web::Data<dyn Store>>()
+            .expect("the store is registered as app data")
+            .clone();
+        Box::pin(async move {
+            let token_hash = token_hash.ok_or(ApiError::Unauthenticated)?;
+            blocking::read(&db, move |db| authenticate(db, &token_hash)).await
+        })
+    }
+}
+
+fn bearer_token(request: &HttpRequest) -> Option<&str> {
+    let value = request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then_some(token.trim())
+}
+
+fn authenticate(db: &dyn Store, token_hash: &str) -> Result<Authenticated, ApiError> {
+    let session = db
+        .find_session(token_hash)?
+        .filter(|session| session.expires_at > Utc::now())
+        .ok_or(ApiError::Unauthenticated)?;
+    let user = db
+        .find_user(&session.user_id)?
+        .ok_or(ApiError::Unauthenticated)?;
+    Ok(Authenticated {
+        user,
+        token_hash: session.token_hash,
+    })
+}
+
+fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("the default parameters accept passwords of any valid length")
+        .to_string()
+}
+
+fn verify_password(password: &str, password_hash: &str) -> bool {
+    PasswordHash::new(password_hash).is_ok_and(|password_hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &password_hash)
+            .is_ok()
+    })
+}
+
+/// Compared against when nobody has the name, so a login for an unknown name
+/// takes as long as one with a wrong password.
+fn unknown_user_hash() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| hash_password("not the password of anyone"))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+/// What sessions are stored under instead of their token. The tokens are random,
+/// so a fast unsalted hash is enough to keep them from being guessed back.
+fn hash_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+/// What clients get to see of a user.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UserResponseData {
+    #[serde(rename = "_id")]
+    id: String,
+    name: String,
+    created_at: DateTime<Utc>,
+}
+
+impl From<User> for UserResponseData {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            name: user.name,
+            created_at: user.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RegisterRequestData {
+    name: String,
+    password: String,
+}
+impl Validate for RegisterRequestData {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.name("name", &mut self.name);
+        validator.password("password", &self.password);
+        validator.finish()
+    }
+}
+async fn register(
+    body: web::Json<RegisterRequestData>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let mut request_data = body.into_inner();
+    request_data.validate()?;
+    let user = blocking::write(&db, move |db| {
+        // the store rejects a name that is taken as well, but only once the password is hashed
+        if db.find_user_by_name(&request_data.name)?.is_some() {
+            return Err(ApiError::conflict("user", "name", &request_data.name));
+        }
+        let now = Utc::now();
+        let user = User {
+            id: Uuid::new_v4().to_string(),
+            name: request_data.name,
+            password_hash: hash_password(&request_data.password),
+            created_at: now,
+            updated_at: now,
+        };
+        db.append_user(user.clone())?;
+        Ok(user)
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::CREATED).json(UserResponseData::from(user)))
+}
+
+#[derive(Deserialize)]
+struct LoginRequestData {
+    name: String,
+    password: String,
+}
+impl Validate for LoginRequestData {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.name("name", &mut self.name);
+        validator.password("password", &self.password);
+        validator.finish()
+    }
+}
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LoginResponseData {
+    token: String,
+    expires_at: DateTime<Utc>,
+    user: UserResponseData,
+}
+async fn login(
+    body: web::Json<LoginRequestData>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let mut request_data = body.into_inner();
+    request_data.validate()?;
+    let token = generate_token();
+    let token_hash = hash_token(&token);
+    let (session, user) = blocking::write(&db, move |db| {
+        let user = db.find_user_by_name(&request_data.name)?;
+        let password_hash = user
+            .as_ref()
+            .map_or(unknown_user_hash(), |user| &user.password_hash);
+        let is_valid = verify_password(&request_data.password, password_hash);
+        let Some(user) = user.filter(|_| is_valid) else {
+            return Err(ApiError::InvalidCredentials);
+        };
+        let now = Utc::now();
+        let session = Session {
+            token_hash,
+            user_id: user.id.clone(),
+            created_at: now,
+            expires_at: now + Duration::days(SESSION_LIFETIME_DAYS),
+        };
+        db.append_session(session.clone())?;
+        Ok((session, user))
+    })
+    .await?;
+    // the only time the token itself leaves the server
+    let response_data = LoginResponseData {
+        token,
+        expires_at: session.expires_at,
+        user: user.into(),
+    };
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(response_data))
+}
+
+/// Ends the session of the token the request is made with.
+async fn logout(auth: Authenticated, db: web::Data<dyn Store>) -> Result<HttpResponse, ApiError> {
+    blocking::write(&db, move |db| Ok(db.delete_session(&auth.token_hash)?)).await?;
+    Ok(HttpResponseBuilder::new(StatusCode::NO_CONTENT).finish())
+}
+
+pub fn configure_routes(config: &mut ServiceConfig) {
+    config.route("/register", web::post().to(register));
+    config.route("/login", web::post().to(login));
+    config.route("/logout", web::post().to(logout));
+}
@@ -0,0 +1,27 @@
+use actix_web::web;
+
+use crate::storage::Store;
+
+use super::error::ApiError;
+
+/// Runs store calls that only read on the blocking thread pool, so a worker never waits
+/// for a lock or the disk while other requests are queued behind it.
+pub async fn read<F, R>(db: &web::Data<dyn Store>, f: F) -> Result<R, ApiError>
+where
+    F: FnOnce(&dyn Store) -> Result<R, ApiError> + Send + 'static,
+    R: Send + 'static,
+{
+    let db = db.clone().into_inner();
+    web::block(move || f(&*db)).await?
+}
+
+/// Runs store calls that write on the blocking thread pool. They only return once
+/// the change is persisted, the worker serves other requests in the meantime.
+pub async fn write<F, R>(db: &web::Data<dyn Store>, f: F) -> Result<R, ApiError>
+where
+    F: FnOnce(&dyn Store) -> Result<R, ApiError> + Send + 'static,
+    R: Send + 'static,
+{
+    let db = db.clone().into_inner();
+    web::block(move || f(&*db)).await?
+}
@@ -1,108 +1,472 @@
+use std::collections::HashSet;
+
 use actix_web::{
     http::StatusCode,
     web::{self, ServiceConfig},
-    HttpResponseBuilder, Responder,
+    HttpRequest, HttpResponse, HttpResponseBuilder,
 };
+use chrono::{DateTime, Datelike, Duration, FixedOffset, TimeZone, Utc};
 use serde::Deserialize;
 use uuid::Uuid;
 
-use crate::prototype_db::Database;
+use crate::{
+    models::{
+        entry::{Entry, Priority},
+        membership::Role,
+        parent_and_children::{ParentAndChildrenTree, Progress},
+        recurrence::Recurrence,
+        user::User,
+    },
+    storage::{
+        hierarchy,
+        query::{EntryFilter, Page, Paginated, Sort, SortKey, SortOrder},
+        Store,
+    },
+};
+
+use super::{
+    auth::Authenticated,
+    blocking,
+    error::ApiError,
+    list::find_list_as,
+    pagination::{paginated_response, validate_page},
+    tag::find_own_tag,
+    validation::{Validate, Validator},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetEntriesQuery {
+    updated_since: Option<DateTime<Utc>>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    sort: Option<SortKey>,
+    order: Option<SortOrder>,
+    done: Option<bool>,
+    list_id: Option<String>,
+    /// name of a tag, ignoring case
+    tag: Option<String>,
+}
+impl Validate for GetEntriesQuery {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validate_page(&mut validator, self.limit);
+        validator.optional_id("listId", &self.list_id);
+        validator.optional_name("tag", &mut self.tag);
+        validator.finish()
+    }
+}
+async fn get_entries(
+    Authenticated { user, .. }: Authenticated,
+    request: HttpRequest,
+    query: web::Query<GetEntriesQuery>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let mut query = query.into_inner();
+    query.validate()?;
+    let sort = Sort {
+        key: query.sort.unwrap_or_default(),
+        order: query.order.unwrap_or_default(),
+    };
+    let page = Page {
+        limit: query.limit,
+        offset: query.offset.unwrap_or(0),
+    };
+    let entries = blocking::read(&db, move |db| {
+        let tag_id = match &query.tag {
+            Some(name) => match db.find_tag_by_name(&user.id, name)? {
+                Some(tag) => Some(tag.id),
+                // no entry can have a tag that does not exist or belongs to someone else
+                None => {
+                    return Ok(Paginated::<Entry> {
+                        items: Vec::new(),
+                        total: 0,
+                    })
+                }
+            },
+            None => None,
+        };
+        let filter = EntryFilter {
+            list_id: query.list_id,
+            done: query.done,
+            updated_since: query.updated_since,
+            tag_id,
+            member_id: Some(user.id),
+            ..Default::default()
+        };
+        Ok(db.query_entries(&filter, sort, page)?)
+    })
+    .await?;
+    Ok(paginated_response(&request, page, entries))
+}
+
+/// Which due entries `get_due_entries` responds with.
+#[derive(Clone, Copy)]
+enum Due {
+    /// not done and due before now
+    Overdue,
+    /// due on the current day
+    Today,
+    /// due in the current week, which starts on monday
+    ThisWeek,
+}
+
+/// Minutes the time zones in use today are ahead of or behind UTC.
+const MAX_UTC_OFFSET: i32 = 14 * 60;
+const MIN_UTC_OFFSET: i32 = -12 * 60;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetDueEntriesQuery {
+    /// minutes the client's time zone is ahead of UTC, which decides when its days start
+    utc_offset: Option<i32>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    done: Option<bool>,
+    list_id: Option<String>,
+}
+impl Validate for GetDueEntriesQuery {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validate_page(&mut validator, self.limit);
+        validator.optional_id("listId", &self.list_id);
+        if let Some(utc_offset) = self.utc_offset {
+            if !(MIN_UTC_OFFSET..=MAX_UTC_OFFSET).contains(&utc_offset) {
+                validator.error(
+                    "utcOffset",
+                    "out_of_range",
+                    format!("must be between {} and {}", MIN_UTC_OFFSET, MAX_UTC_OFFSET),
+                );
+            }
+        }
+        validator.finish()
+    }
+}
+
+/// Returns when the day that `now` falls on starts in the time zone.
+fn start_of_day(now: DateTime<Utc>, time_zone: FixedOffset) -> DateTime<Utc> {
+    let midnight = now
+        .with_timezone(&time_zone)
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    time_zone
+        .from_local_datetime(&midnight)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+/// Responds with the entries of all lists of the user that are due in the given period,
+/// the ones that are due first come first.
+async fn get_due_entries(
+    due: Due,
+    user: User,
+    request: HttpRequest,
+    query: web::Query<GetDueEntriesQuery>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let mut query = query.into_inner();
+    query.validate()?;
+    let now = Utc::now();
+    let time_zone = FixedOffset::east_opt(query.utc_offset.unwrap_or(0) * 60).unwrap();
+    let today = start_of_day(now, time_zone);
+    let (done, due_from, due_before) = match due {
+        Due::Overdue => (Some(false), None, now),
+        Due::Today => (query.done, Some(today), today + Duration::days(1)),
+        Due::ThisWeek => {
+            let days_since_monday = now
+                .with_timezone(&time_zone)
+                .weekday()
+                .num_days_from_monday();
+            let monday = today - Duration::days(days_since_monday.into());
+            (query.done, Some(monday), monday + Duration::days(7))
+        }
+    };
+    let filter = EntryFilter {
+        list_id: query.list_id,
+        done,
+        due_from,
+        due_before: Some(due_before),
+        member_id: Some(user.id),
+        ..Default::default()
+    };
+    let sort = Sort {
+        key: SortKey::Due,
+        order: SortOrder::Asc,
+    };
+    let page = Page {
+        limit: query.limit,
+        offset: query.offset.unwrap_or(0),
+    };
+    let entries = blocking::read(&db, move |db| Ok(db.query_entries(&filter, sort, page)?)).await?;
+    Ok(paginated_response(&request, page, entries))
+}
+
+async fn get_overdue_entries(
+    Authenticated { user, .. }: Authenticated,
+    request: HttpRequest,
+    query: web::Query<GetDueEntriesQuery>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    get_due_entries(Due::Overdue, user, request, query, db).await
+}
+
+async fn get_entries_due_today(
+    Authenticated { user, .. }: Authenticated,
+    request: HttpRequest,
+    query: web::Query<GetDueEntriesQuery>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    get_due_entries(Due::Today, user, request, query, db).await
+}
+
+async fn get_entries_due_this_week(
+    Authenticated { user, .. }: Authenticated,
+    request: HttpRequest,
+    query: web::Query<GetDueEntriesQuery>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    get_due_entries(Due::ThisWeek, user, request, query, db).await
+}
 
-async fn get_entries(db: web::Data<Database>) -> impl Responder {
-    let entry_collection_mutex = db.get_entry_collection();
-    let entry_collection = entry_collection_mutex.lock().unwrap();
+/// Returns the entry if the user has at least the role on its list, entries of lists
+/// the user has no access to are reported as missing like the lists themselves.
+fn find_entry_as(db: &dyn Store, id: &str, user: &User, required: Role) -> Result<Entry, ApiError> {
+    let entry = db
+        .find_entry(id)?
+        .ok_or_else(|| ApiError::not_found("entry", id))?;
+    match find_list_as(db, &entry.list_id, user, required) {
+        Ok(_) => Ok(entry),
+        Err(ApiError::NotFound { .. }) => Err(ApiError::not_found("entry", id)),
+        Err(error) => Err(error),
+    }
+}
 
-    let entries = entry_collection.get_all();
-    HttpResponseBuilder::new(StatusCode::OK).json(entries)
+/// Builds the tree of the entry's sub-entries out of all entries of its list.
+/// Entries that are already part of the tree are skipped, so a cycle in corrupt data
+/// cannot recurse forever.
+pub(super) fn entry_tree<'a>(
+    entry: &'a Entry,
+    entries: &'a [Entry],
+    visited: &mut HashSet<&'a str>,
+) -> ParentAndChildrenTree<Entry> {
+    let children: Vec<&Entry> = entries
+        .iter()
+        .filter(|child| child.parent_id.as_deref() == Some(entry.id.as_str()))
+        .filter(|child| visited.insert(&child.id))
+        .collect();
+    let progress = Progress {
+        done: children.iter().filter(|child| child.done).count(),
+        total: children.len(),
+    };
+    let children = children
+        .into_iter()
+        .map(|child| entry_tree(child, entries, visited))
+        .collect();
+    ParentAndChildrenTree {
+        parent: entry.clone(),
+        progress,
+        children,
+    }
 }
 
-async fn get_entry(id: web::Path<String>, db: web::Data<Database>) -> impl Responder {
-    let entry_collection_mutex = db.get_entry_collection();
-    let entry_collection = entry_collection_mutex.lock().unwrap();
+async fn get_entry_tree(
+    Authenticated { user, .. }: Authenticated,
+    id: web::Path<String>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
-    let entry_option = entry_collection.find_one(|model| model.id == id);
-    if entry_option.is_none() {
-        return HttpResponseBuilder::new(StatusCode::NOT_FOUND).json("not found");
+    let (entry, entries) = blocking::read(&db, {
+        let id = id.clone();
+        move |db| {
+            let entry = find_entry_as(db, &id, &user, Role::Viewer)?;
+            let entries = db.find_entries_of_list(&entry.list_id)?;
+            Ok((entry, entries))
+        }
+    })
+    .await?;
+    let tree = entry_tree(&entry, &entries, &mut HashSet::from([id.as_str()]));
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(tree))
+}
+
+/// Checks that the parent exists in the list and, for an existing entry,
+/// that the entry does not become a sub-entry of itself.
+fn check_parent(
+    db: &dyn Store,
+    user: &User,
+    parent_id: &str,
+    list_id: &str,
+    entry_id: Option<&str>,
+) -> Result<(), ApiError> {
+    let parent = find_entry_as(db, parent_id, user, Role::Viewer)?;
+    let mut validator = Validator::new();
+    if parent.list_id != list_id {
+        validator.error(
+            "parentId",
+            "other_list",
+            "must be an entry of the same list",
+        );
+    } else if let Some(entry_id) = entry_id {
+        let entries = db.find_entries_of_list(list_id)?;
+        let entries: Vec<&Entry> = entries.iter().collect();
+        if parent_id == entry_id
+            || hierarchy::descendant_ids(&entries, entry_id).contains(&parent.id)
+        {
+            validator.error(
+                "parentId",
+                "cycle",
+                "must not be the entry itself or one of its sub-entries",
+            );
+        }
     }
-    let entry = entry_option.unwrap();
-    HttpResponseBuilder::new(StatusCode::OK).json(entry)
+    validator.finish()
+}
+
+async fn get_entry(
+    Authenticated { user, .. }: Authenticated,
+    id: web::Path<String>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let entry = blocking::read(&db, move |db| find_entry_as(db, &id, &user, Role::Viewer)).await?;
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(entry))
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct PostEntryRequestData {
     list_id: String,
+    parent_id: Option<String>,
     name: String,
     done: Option<bool>,
+    due_at: Option<DateTime<Utc>>,
+    priority: Option<Priority>,
+    notes: Option<String>,
+    recurrence: Option<Recurrence>,
+}
+impl Validate for PostEntryRequestData {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.id("listId", &self.list_id);
+        validator.optional_id("parentId", &self.parent_id);
+        validator.name("name", &mut self.name);
+        if let Some(notes) = &self.notes {
+            validator.notes("notes", notes);
+        }
+        if let Some(recurrence) = &self.recurrence {
+            validator.recurrence(recurrence);
+        }
+        validator.finish()
+    }
 }
 async fn post_entry(
+    Authenticated { user, .. }: Authenticated,
     body: web::Json<PostEntryRequestData>,
-    db: web::Data<Database>,
-) -> impl Responder {
-    let list_collection_mutex = db.get_list_collection();
-    let list_collection = list_collection_mutex.lock().unwrap();
-    let request_data = body.into_inner();
-    let list_id = request_data.list_id.clone();
-    let list_option = list_collection.find_one(|model| model.id == list_id);
-    if list_option.is_none() {
-        return HttpResponseBuilder::new(StatusCode::NOT_FOUND).json("list not found");
-    }
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let mut request_data = body.into_inner();
+    request_data.validate()?;
     let uuidv4 = Uuid::new_v4().to_string();
+    let now = Utc::now();
     let new_model = crate::models::entry::Entry {
         id: uuidv4,
         list_id: request_data.list_id,
+        parent_id: request_data.parent_id,
         name: request_data.name,
         done: request_data.done.unwrap_or(false),
+        // the store adds it after the last entry of the list
+        position: 0,
+        due_at: request_data.due_at,
+        priority: request_data.priority,
+        notes: request_data.notes,
+        recurrence: request_data.recurrence,
+        created_at: now,
+        updated_at: now,
     };
 
-    let entry_collection_mutex = db.get_entry_collection();
-    let mut entry_collection = entry_collection_mutex.lock().unwrap();
-    let save_result = entry_collection.append(new_model.clone());
-    if let Err(e) = save_result {
-        return HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR).json(e.to_string());
-    }
-    HttpResponseBuilder::new(StatusCode::CREATED).json(&new_model)
+    let new_model = blocking::write(&db, move |db| {
+        find_list_as(db, &new_model.list_id, &user, Role::Editor)?;
+        if let Some(parent_id) = &new_model.parent_id {
+            check_parent(db, &user, parent_id, &new_model.list_id, None)?;
+        }
+        Ok(db.append_entry(new_model)?)
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::CREATED).json(&new_model))
 }
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct PatchEntryRequestData {
     list_id: Option<String>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    parent_id: Option<Option<String>>,
     name: Option<String>,
     done: Option<bool>,
+    // the outer option tells whether the field was sent, null removes the value
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    due_at: Option<Option<DateTime<Utc>>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    priority: Option<Option<Priority>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    notes: Option<Option<String>>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    recurrence: Option<Option<Recurrence>>,
+    /// moves the entry to this index among the entries of its list
+    index: Option<usize>,
+}
+impl Validate for PatchEntryRequestData {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.optional_id("listId", &self.list_id);
+        if let Some(parent_id) = &self.parent_id {
+            validator.optional_id("parentId", parent_id);
+        }
+        validator.optional_name("name", &mut self.name);
+        if let Some(Some(notes)) = &self.notes {
+            validator.notes("notes", notes);
+        }
+        if let Some(Some(recurrence)) = &self.recurrence {
+            validator.recurrence(recurrence);
+        }
+        validator.finish()
+    }
 }
 async fn patch_entry(
+    Authenticated { user, .. }: Authenticated,
     body: web::Json<PatchEntryRequestData>,
-    db: web::Data<Database>,
+    db: web::Data<dyn Store>,
     id: web::Path<String>,
-) -> impl Responder {
-    let entry_collection_mutex = db.get_entry_collection();
-    let mut entry_collection = entry_collection_mutex.lock().unwrap();
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
-    let body = body.into_inner();
-
-    // if a list_id is provided this checks if the list exists
-    // and if it doesn't it returns an error message
-    let list_id_checked_option = if let Some(list_id) = &body.list_id {
-        let list_exists = db
-            .get_list_collection()
-            .lock()
-            .unwrap()
-            .find_one(|model| model.id == list_id.clone())
-            .is_some();
-        if !list_exists {
-            return HttpResponseBuilder::new(StatusCode::NOT_FOUND).json("list not found");
-        }
-        Some(list_id.clone())
-    } else {
-        None
-    };
+    let mut body = body.into_inner();
+    body.validate()?;
 
-    let save_result = entry_collection.patch_one(
-        move |model| model.id == id,
-        move |model| {
-            if let Some(list_id) = list_id_checked_option {
-                model.list_id = list_id;
+    let model = blocking::write(&db, move |db| {
+        let entry = find_entry_as(db, &id, &user, Role::Editor)?;
+        // an entry can only be moved to another list the user may edit
+        if let Some(list_id) = &body.list_id {
+            find_list_as(db, list_id, &user, Role::Editor)?;
+        }
+
+        if let Some(Some(parent_id)) = &body.parent_id {
+            let list_id = body.list_id.as_ref().unwrap_or(&entry.list_id);
+            check_parent(db, &user, parent_id, list_id, Some(&id))?;
+        }
+
+        let update = &mut |model: &mut Entry| {
+            if let Some(parent_id) = &body.parent_id {
+                model.parent_id = parent_id.clone();
+            } else if body
+                .list_id
+                .as_ref()
+                .is_some_and(|list_id| list_id != &model.list_id)
+            {
+                // the parent stays in the old list
+                model.parent_id = None;
+            }
+            if let Some(list_id) = &body.list_id {
+                model.list_id = list_id.clone();
             }
             if let Some(name) = &body.name {
                 model.name = name.clone();
@@ -110,66 +474,242 @@ async fn patch_entry(
             if let Some(done) = &body.done {
                 model.done = *done;
             }
-        },
-    );
-    if let Err(e) = save_result {
-        return HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR).json(e.to_string());
-    }
-    let model_option = save_result.unwrap();
-    if model_option.is_none() {
-        return HttpResponseBuilder::new(StatusCode::NOT_FOUND).json("not found");
-    }
-    let model = model_option.unwrap();
-    HttpResponseBuilder::new(StatusCode::OK).json(model)
+            if let Some(due_at) = &body.due_at {
+                model.due_at = *due_at;
+            }
+            if let Some(priority) = &body.priority {
+                model.priority = *priority;
+            }
+            if let Some(notes) = &body.notes {
+                model.notes = notes.clone();
+            }
+            if let Some(recurrence) = &body.recurrence {
+                model.recurrence = recurrence.clone();
+            }
+            model.updated_at = Utc::now();
+        };
+        // completing a recurring entry adds its next occurrence along with it
+        let follow_up = &mut |old: &Entry, new: &Entry| {
+            if new.done && !old.done {
+                next_occurrence(new)
+            } else {
+                None
+            }
+        };
+        let model = db
+            .patch_entry_with_follow_up(&id, update, follow_up)?
+            .ok_or_else(|| ApiError::not_found("entry", &id))?;
+        match body.index {
+            Some(index) => db
+                .move_entry(&id, index)?
+                .ok_or_else(|| ApiError::not_found("entry", &id)),
+            None => Ok(model),
+        }
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(model))
 }
 
-async fn delete_entry(db: web::Data<Database>, id: web::Path<String>) -> impl Responder {
-    let entry_collection_mutex = db.get_entry_collection();
-    let mut entry_collection = entry_collection_mutex.lock().unwrap();
+async fn get_tags_of_entry(
+    Authenticated { user, .. }: Authenticated,
+    id: web::Path<String>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
-    let save_result = entry_collection.delete_one(|model| model.id == id);
-    if let Err(e) = save_result {
-        return HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR).json(e.to_string());
-    }
-    HttpResponseBuilder::new(StatusCode::NO_CONTENT).finish()
+    let tags = blocking::read(&db, move |db| {
+        find_entry_as(db, &id, &user, Role::Viewer)?;
+        // members of a shared list tag its entries with their own tags, each only sees theirs
+        let tags: Vec<_> = db
+            .find_tags_of_entry(&id)?
+            .into_iter()
+            .filter(|tag| tag.owner_id.as_deref() == Some(user.id.as_str()))
+            .collect();
+        Ok(tags)
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(tags))
+}
+
+/// Checks that the user may edit the entry and owns the tag.
+fn check_entry_and_tag(
+    db: &dyn Store,
+    user: &User,
+    entry_id: &str,
+    tag_id: &str,
+) -> Result<(), ApiError> {
+    find_entry_as(db, entry_id, user, Role::Editor)?;
+    find_own_tag(db, tag_id, user)?;
+    Ok(())
+}
+
+async fn tag_entry(
+    Authenticated { user, .. }: Authenticated,
+    path: web::Path<(String, String)>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let (id, tag_id) = path.into_inner();
+    blocking::write(&db, move |db| {
+        check_entry_and_tag(db, &user, &id, &tag_id)?;
+        Ok(db.tag_entry(&id, &tag_id)?)
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::NO_CONTENT).finish())
+}
+
+async fn untag_entry(
+    Authenticated { user, .. }: Authenticated,
+    path: web::Path<(String, String)>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let (id, tag_id) = path.into_inner();
+    blocking::write(&db, move |db| {
+        check_entry_and_tag(db, &user, &id, &tag_id)?;
+        Ok(db.untag_entry(&id, &tag_id)?)
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::NO_CONTENT).finish())
+}
+
+/// Returns the occurrence that follows a completed recurring entry, unless its rule has ended.
+/// The new occurrence is not done and carries the rule on, the completed entry keeps it too,
+/// so it still recurs if it is completed again after being reopened. The id of the next
+/// occurrence is derived from the id of the entry, so that adds no second one.
+fn next_occurrence(entry: &Entry) -> Option<Entry> {
+    let now = Utc::now();
+    // without a due date the rule counts from when the entry was completed
+    let (due_at, recurrence) = entry
+        .recurrence
+        .as_ref()?
+        .next(entry.due_at.unwrap_or(now))?;
+    Some(Entry {
+        id: Uuid::new_v5(&Uuid::NAMESPACE_OID, entry.id.as_bytes()).to_string(),
+        list_id: entry.list_id.clone(),
+        parent_id: entry.parent_id.clone(),
+        name: entry.name.clone(),
+        done: false,
+        // the store adds it after the last entry of the list
+        position: 0,
+        due_at: Some(due_at),
+        priority: entry.priority,
+        notes: entry.notes.clone(),
+        recurrence: Some(recurrence),
+        created_at: now,
+        updated_at: now,
+    })
+}
+
+async fn delete_entry(
+    Authenticated { user, .. }: Authenticated,
+    db: web::Data<dyn Store>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    blocking::write(&db, move |db| {
+        match find_entry_as(db, &id, &user, Role::Editor) {
+            Ok(_) => {
+                db.delete_entry(&id)?;
+                Ok(())
+            }
+            // entries of lists the user has no access to are left alone, like ones that don't exist
+            Err(ApiError::NotFound { .. }) => Ok(()),
+            Err(error) => Err(error),
+        }
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::NO_CONTENT).finish())
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct PutEntryRequestData {
     list_id: String,
+    parent_id: Option<String>,
     name: String,
     done: bool,
+    due_at: Option<DateTime<Utc>>,
+    priority: Option<Priority>,
+    notes: Option<String>,
+    recurrence: Option<Recurrence>,
 }
+impl Validate for PutEntryRequestData {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.id("listId", &self.list_id);
+        validator.optional_id("parentId", &self.parent_id);
+        validator.name("name", &mut self.name);
+        if let Some(notes) = &self.notes {
+            validator.notes("notes", notes);
+        }
+        if let Some(recurrence) = &self.recurrence {
+            validator.recurrence(recurrence);
+        }
+        validator.finish()
+    }
+}
+/// Replaces the entry or creates it with the given id, responds with 201 if it was created.
 async fn put_entry(
-    db: web::Data<Database>,
+    Authenticated { user, .. }: Authenticated,
+    db: web::Data<dyn Store>,
     body: web::Json<PutEntryRequestData>,
     id: web::Path<String>,
-) -> impl Responder {
-    let entry_collection_mutex = db.get_entry_collection();
-    let mut entry_collection = entry_collection_mutex.lock().unwrap();
-    let request_data = body.into_inner();
-    let list_id = request_data.list_id.clone();
-    let list_option = entry_collection.find_one(|model| model.id == list_id);
-    if list_option.is_none() {
-        return HttpResponseBuilder::new(StatusCode::NOT_FOUND).json("list not found");
-    }
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
-    let new_model = crate::models::entry::Entry {
+    let mut request_data = body.into_inner();
+    request_data.validate()?;
+    let now = Utc::now();
+    let new_model = Entry {
         id,
         list_id: request_data.list_id,
+        parent_id: request_data.parent_id,
         name: request_data.name,
         done: request_data.done,
+        // the store keeps the position of a replaced entry
+        // and adds a new one after the last entry of the list
+        position: 0,
+        due_at: request_data.due_at,
+        priority: request_data.priority,
+        notes: request_data.notes,
+        recurrence: request_data.recurrence,
+        created_at: now,
+        updated_at: now,
     };
-    let save_result = entry_collection.append(new_model.clone());
-    if let Err(e) = save_result {
-        return HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR).json(e.to_string());
-    }
-    HttpResponseBuilder::new(StatusCode::CREATED).json(&new_model)
+    let (model, created) = blocking::write(&db, move |db| {
+        find_list_as(db, &new_model.list_id, &user, Role::Editor)?;
+        // an existing entry can only be replaced by someone who may edit its list
+        if db.find_entry(&new_model.id)?.is_some() {
+            find_entry_as(db, &new_model.id, &user, Role::Editor)?;
+        }
+        if let Some(parent_id) = &new_model.parent_id {
+            check_parent(
+                db,
+                &user,
+                parent_id,
+                &new_model.list_id,
+                Some(&new_model.id),
+            )?;
+        }
+        Ok(db.put_entry(new_model)?)
+    })
+    .await?;
+    let status = if created {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    Ok(HttpResponseBuilder::new(status).json(&model))
 }
 
 pub fn configure_routes(config: &mut ServiceConfig) {
     config.route("", web::get().to(get_entries));
+    // registered before "/{id}", which would match them as well
+    config.route("/overdue", web::get().to(get_overdue_entries));
+    config.route("/due-today", web::get().to(get_entries_due_today));
+    config.route("/due-this-week", web::get().to(get_entries_due_this_week));
     config.route("/{id}", web::get().to(get_entry));
+    config.route("/{id}/tree", web::get().to(get_entry_tree));
+    config.route("/{id}/tags", web::get().to(get_tags_of_entry));
+    config.route("/{id}/tags/{tag_id}", web::put().to(tag_entry));
+    config.route("/{id}/tags/{tag_id}", web::delete().to(untag_entry));
     config.route("", web::post().to(post_entry));
     config.route("/{id}", web::patch().to(patch_entry));
     config.route("/{id}", web::delete().to(delete_entry));
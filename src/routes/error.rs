@@ -0,0 +1,216 @@
+use std::{fmt, io};
+
+use actix_web::{
+    error::{BlockingError, JsonPayloadError, QueryPayloadError},
+    http::{header, StatusCode},
+    HttpRequest, HttpResponse, HttpResponseBuilder, ResponseError,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::{models::membership::Role, prototype_db::WriteError, storage::StoreError};
+
+use super::validation::FieldError;
+
+/// Every error a handler can respond with.
+/// The response body is a problem details object as described in RFC 7807,
+/// extended by a machine-readable `code` and optional `details`.
+#[derive(Debug)]
+pub enum ApiError {
+    NotFound {
+        resource: &'static str,
+        id: String,
+    },
+    /// another resource already has the value of a field that has to be unique
+    Conflict {
+        resource: &'static str,
+        field: &'static str,
+        value: String,
+    },
+    /// the body is valid json of the right shape, but its values are not acceptable
+    Validation(Vec<FieldError>),
+    /// the body could not be read or deserialized
+    InvalidPayload(JsonPayloadError),
+    /// the query string could not be deserialized
+    InvalidQuery(QueryPayloadError),
+    /// the request has no bearer token of a session that is still valid
+    Unauthenticated,
+    /// the name and password of a login don't match any user
+    InvalidCredentials,
+    /// the user has access to the list, but a role below the one the request requires
+    Forbidden {
+        required: Role,
+    },
+    Storage(StoreError),
+}
+
+#[derive(Serialize)]
+struct ProblemDetails {
+    #[serde(rename = "type")]
+    problem_type: &'static str,
+    title: &'static str,
+    status: u16,
+    code: String,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<Value>,
+}
+
+impl ApiError {
+    pub fn not_found(resource: &'static str, id: &str) -> Self {
+        ApiError::NotFound {
+            resource,
+            id: id.to_string(),
+        }
+    }
+
+    pub fn conflict(resource: &'static str, field: &'static str, value: &str) -> Self {
+        ApiError::Conflict {
+            resource,
+            field,
+            value: value.to_string(),
+        }
+    }
+
+    pub fn code(&self) -> String {
+        match self {
+            ApiError::NotFound { resource, .. } => format!("{}_not_found", resource),
+            ApiError::Conflict { resource, .. } => format!("{}_already_exists", resource),
+            ApiError::Validation(_) => "validation_failed".to_string(),
+            ApiError::InvalidPayload(e) => match e {
+                JsonPayloadError::ContentType => "unsupported_content_type",
+                JsonPayloadError::Overflow { .. }
+                | JsonPayloadError::OverflowKnownLength { .. } => "payload_too_large",
+                JsonPayloadError::Deserialize(e) if e.is_data() => "invalid_payload",
+                _ => "malformed_json",
+            }
+            .to_string(),
+            ApiError::InvalidQuery(_) => "invalid_query".to_string(),
+            ApiError::Unauthenticated => "unauthenticated".to_string(),
+            ApiError::InvalidCredentials => "invalid_credentials".to_string(),
+            ApiError::Forbidden { .. } => "forbidden".to_string(),
+            ApiError::Storage(_) => "storage_error".to_string(),
+        }
+    }
+
+    fn details(&self) -> Option<Value> {
+        match self {
+            ApiError::NotFound { resource, id } => Some(json!({ "resource": resource, "id": id })),
+            ApiError::Conflict {
+                resource,
+                field,
+                value,
+            } => Some(json!({ "resource": resource, "field": field, "value": value })),
+            ApiError::Validation(errors) => Some(json!({ "errors": errors })),
+            ApiError::Forbidden { required } => Some(json!({ "requiredRole": required })),
+            ApiError::InvalidPayload(_)
+            | ApiError::InvalidQuery(_)
+            | ApiError::Unauthenticated
+            | ApiError::InvalidCredentials
+            | ApiError::Storage(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::NotFound { resource, .. } => write!(f, "{} not found", resource),
+            ApiError::Conflict {
+                resource, field, ..
+            } => write!(f, "a {} with this {} already exists", resource, field),
+            ApiError::Validation(errors) => {
+                write!(f, "{} field(s) of the request are invalid", errors.len())
+            }
+            ApiError::InvalidPayload(e) => write!(f, "{}", e),
+            ApiError::InvalidQuery(e) => write!(f, "{}", e),
+            ApiError::Unauthenticated => write!(f, "a valid bearer token is required"),
+            ApiError::InvalidCredentials => write!(f, "the name or the password is wrong"),
+            ApiError::Forbidden { required } => {
+                write!(f, "this requires the role {} on the list", required)
+            }
+            // the cause is logged but not exposed to clients
+            ApiError::Storage(_) => write!(f, "the data could not be read or written"),
+        }
+    }
+}
+
+impl From<StoreError> for ApiError {
+    fn from(e: StoreError) -> Self {
+        match e {
+            StoreError::Write(WriteError::Duplicate {
+                collection,
+                index,
+                key,
+            }) => ApiError::Conflict {
+                resource: collection,
+                field: index,
+                value: key,
+            },
+            // what was checked before went away while the request was handled
+            StoreError::Write(WriteError::Missing { collection, id }) => ApiError::NotFound {
+                resource: collection,
+                id,
+            },
+            e => ApiError::Storage(e),
+        }
+    }
+}
+
+impl From<BlockingError> for ApiError {
+    fn from(e: BlockingError) -> Self {
+        ApiError::Storage(StoreError::Io(io::Error::other(e.to_string())))
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound { .. } => StatusCode::NOT_FOUND,
+            ApiError::Conflict { .. } => StatusCode::CONFLICT,
+            ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::InvalidPayload(e) => match e {
+                JsonPayloadError::ContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                JsonPayloadError::Deserialize(e) if e.is_data() => StatusCode::UNPROCESSABLE_ENTITY,
+                e => e.status_code(),
+            },
+            ApiError::InvalidQuery(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthenticated | ApiError::InvalidCredentials => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden { .. } => StatusCode::FORBIDDEN,
+            ApiError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        if let ApiError::Storage(e) = self {
+            log::error!("storage error: {}", e);
+        }
+        let status = self.status_code();
+        let body = ProblemDetails {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            code: self.code(),
+            detail: self.to_string(),
+            details: self.details(),
+        };
+        let mut response = HttpResponseBuilder::new(status);
+        if status == StatusCode::UNAUTHORIZED {
+            response.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+        }
+        response
+            .insert_header((header::CONTENT_TYPE, "application/problem+json"))
+            .json(body)
+    }
+}
+
+/// Used by the app's `JsonConfig`, so bodies that are not even valid json
+/// are reported in the same format as every other error.
+pub fn json_error_handler(e: JsonPayloadError, _request: &HttpRequest) -> actix_web::Error {
+    ApiError::InvalidPayload(e).into()
+}
+
+/// Used by the app's `QueryConfig`, like `json_error_handler`.
+pub fn query_error_handler(e: QueryPayloadError, _request: &HttpRequest) -> actix_web::Error {
+    ApiError::InvalidQuery(e).into()
+}
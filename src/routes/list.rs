@@ -1,176 +1,359 @@
+use std::collections::HashSet;
+
 use actix_web::{
     http::StatusCode,
     web::{self, ServiceConfig},
-    HttpResponseBuilder, Responder,
+    HttpRequest, HttpResponse, HttpResponseBuilder,
 };
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::{
-    models::{list::List, parent_and_children::ParentAndChildren},
-    prototype_db::Database,
+    models::{
+        entry::Entry,
+        list::List,
+        membership::Role,
+        parent_and_children::{ParentAndChildren, ParentAndChildrenTree},
+        user::User,
+    },
+    storage::{
+        query::{ListFilter, Page, Sort, SortKey, SortOrder},
+        Store,
+    },
 };
 
-async fn get_lists(db: web::Data<Database>) -> impl Responder {
-    let list_collection_mutex = db.get_list_collection();
-    let list_collection = list_collection_mutex.lock().unwrap();
+use super::{
+    auth::Authenticated,
+    blocking,
+    entry::entry_tree,
+    error::ApiError,
+    pagination::{paginated_response, validate_page},
+    validation::{Validate, Validator},
+};
 
-    let lists = list_collection.get_all();
-    HttpResponseBuilder::new(StatusCode::OK).json(lists)
+/// The role the user has on the list, `None` if they have no access to it.
+/// The user who created the list is its owner without a membership.
+pub(super) fn role_on_list(
+    db: &dyn Store,
+    list: &List,
+    user: &User,
+) -> Result<Option<Role>, ApiError> {
+    if list.owner_id.as_ref() == Some(&user.id) {
+        return Ok(Some(Role::Owner));
+    }
+    Ok(db
+        .find_membership(&list.id, &user.id)?
+        .map(|membership| membership.role))
 }
 
-async fn get_list(id: web::Path<String>, db: web::Data<Database>) -> impl Responder {
-    let list_collection_mutex = db.get_list_collection();
-    let list_collection = list_collection_mutex.lock().unwrap();
+/// Returns the list if the user has at least the role on it. Lists the user has no access to
+/// are reported as missing, so nobody learns which ids exist.
+pub(super) fn find_list_as(
+    db: &dyn Store,
+    id: &str,
+    user: &User,
+    required: Role,
+) -> Result<List, ApiError> {
+    let list = db
+        .find_list(id)?
+        .ok_or_else(|| ApiError::not_found("list", id))?;
+    match role_on_list(db, &list, user)? {
+        Some(role) if role >= required => Ok(list),
+        Some(_) => Err(ApiError::Forbidden { required }),
+        None => Err(ApiError::not_found("list", id)),
+    }
+}
 
-    // let lists = list_collection.get_all();
-    let id = id.into_inner();
-    let list_option = list_collection.find_one(|model| model.id == id);
-    if list_option.is_none() {
-        return HttpResponseBuilder::new(StatusCode::NOT_FOUND).json("not found");
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetListsQuery {
+    updated_since: Option<DateTime<Utc>>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    sort: Option<SortKey>,
+    order: Option<SortOrder>,
+}
+impl Validate for GetListsQuery {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validate_page(&mut validator, self.limit);
+        if matches!(self.sort, Some(SortKey::Due | SortKey::Position)) {
+            validator.error(
+                "sort",
+                "unsupported",
+                "lists can only be sorted by name, created or updated",
+            );
+        }
+        validator.finish()
     }
-    let list = list_option.unwrap();
-    HttpResponseBuilder::new(StatusCode::OK).json(list)
+}
+async fn get_lists(
+    Authenticated { user, .. }: Authenticated,
+    request: HttpRequest,
+    query: web::Query<GetListsQuery>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let mut query = query.into_inner();
+    query.validate()?;
+    let sort = Sort {
+        key: query.sort.unwrap_or_default(),
+        order: query.order.unwrap_or_default(),
+    };
+    let page = Page {
+        limit: query.limit,
+        offset: query.offset.unwrap_or(0),
+    };
+    let filter = ListFilter {
+        member_id: Some(user.id),
+        updated_since: query.updated_since,
+        ..Default::default()
+    };
+    let lists = blocking::read(&db, move |db| Ok(db.query_lists(&filter, sort, page)?)).await?;
+    Ok(paginated_response(&request, page, lists))
 }
 
-async fn get_list_and_its_entries(
+async fn get_list(
+    Authenticated { user, .. }: Authenticated,
     id: web::Path<String>,
-    db: web::Data<Database>,
-) -> impl Responder {
-    let list_collection_mutex = db.get_list_collection();
-    let list_collection = list_collection_mutex.lock().unwrap();
-
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
-    let list_option = list_collection.find_one(|model| model.id == id);
-    if list_option.is_none() {
-        return HttpResponseBuilder::new(StatusCode::NOT_FOUND).json("not found");
-    }
-    let list = list_option.unwrap();
+    let list = blocking::read(&db, move |db| find_list_as(db, &id, &user, Role::Viewer)).await?;
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(list))
+}
 
-    let entry_collection_mutex = db.get_entry_collection();
-    let entry_collection = entry_collection_mutex.lock().unwrap();
-    let entries = entry_collection.find(|model| model.list_id == id);
+async fn get_list_and_its_entries(
+    Authenticated { user, .. }: Authenticated,
+    id: web::Path<String>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let (list, entries) = blocking::read(&db, move |db| {
+        let list = find_list_as(db, &id, &user, Role::Viewer)?;
+        Ok((list, db.find_entries_of_list(&id)?))
+    })
+    .await?;
 
     let body = ParentAndChildren {
         parent: list,
         children: &entries,
     };
 
-    HttpResponseBuilder::new(StatusCode::OK).json(body)
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(body))
+}
+
+/// Responds with the list and the trees of its top level entries.
+async fn get_list_tree(
+    Authenticated { user, .. }: Authenticated,
+    id: web::Path<String>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let (list, entries) = blocking::read(&db, move |db| {
+        let list = find_list_as(db, &id, &user, Role::Viewer)?;
+        Ok((list, db.find_entries_of_list(&id)?))
+    })
+    .await?;
+
+    // entries whose parent is missing are shown at the top level, so none get lost
+    let ids: HashSet<&str> = entries.iter().map(|entry| entry.id.as_str()).collect();
+    let top_level: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| {
+            entry
+                .parent_id
+                .as_deref()
+                .is_none_or(|parent_id| !ids.contains(parent_id))
+        })
+        .collect();
+    let mut visited: HashSet<&str> = top_level.iter().map(|entry| entry.id.as_str()).collect();
+    let trees: Vec<ParentAndChildrenTree<Entry>> = top_level
+        .into_iter()
+        .map(|entry| entry_tree(entry, &entries, &mut visited))
+        .collect();
+
+    let body = ParentAndChildren {
+        parent: list,
+        children: &trees,
+    };
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(body))
 }
 
 #[derive(Deserialize)]
 struct PostListRequestData {
     name: String,
 }
+impl Validate for PostListRequestData {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.name("name", &mut self.name);
+        validator.finish()
+    }
+}
 async fn post_list(
+    Authenticated { user, .. }: Authenticated,
     body: web::Json<PostListRequestData>,
-    db: web::Data<Database>,
-) -> impl Responder {
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let mut request_data = body.into_inner();
+    request_data.validate()?;
     let uuidv4 = Uuid::new_v4().to_string();
+    let now = Utc::now();
     let new_model = List {
         id: uuidv4,
-        name: body.into_inner().name,
+        name: request_data.name,
+        owner_id: Some(user.id),
+        created_at: now,
+        updated_at: now,
     };
-    let list_collection_mutex = db.get_list_collection();
-    let mut list_collection = list_collection_mutex.lock().unwrap();
-    let save_result = list_collection.append(new_model.clone());
-    if let Err(e) = save_result {
-        return HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR).json(e.to_string());
-    }
-    HttpResponseBuilder::new(StatusCode::CREATED).json(&new_model)
+    let model = new_model.clone();
+    blocking::write(&db, move |db| Ok(db.append_list(model)?)).await?;
+    Ok(HttpResponseBuilder::new(StatusCode::CREATED).json(&new_model))
 }
 
 #[derive(Deserialize)]
 struct PatchListRequestData {
     name: Option<String>,
 }
+impl Validate for PatchListRequestData {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.optional_name("name", &mut self.name);
+        validator.finish()
+    }
+}
 async fn patch_list(
+    Authenticated { user, .. }: Authenticated,
     body: web::Json<PatchListRequestData>,
-    db: web::Data<Database>,
+    db: web::Data<dyn Store>,
     id: web::Path<String>,
-) -> impl Responder {
-    let list_collection_mutex = db.get_list_collection();
-    let mut list_collection = list_collection_mutex.lock().unwrap();
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
-    let body = body.into_inner();
-    let save_result = list_collection.patch_one(
-        move |model| model.id == id,
-        move |model| {
+    let mut body = body.into_inner();
+    body.validate()?;
+    let model = blocking::write(&db, move |db| {
+        find_list_as(db, &id, &user, Role::Editor)?;
+        db.patch_list(&id, &mut |model| {
             if let Some(name) = &body.name {
                 model.name = name.clone();
             }
-        },
-    );
-    if let Err(e) = save_result {
-        return HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR).json(e.to_string());
-    }
-    let model_option = save_result.unwrap();
-    if model_option.is_none() {
-        return HttpResponseBuilder::new(StatusCode::NOT_FOUND).json("not found");
-    }
-    let body = model_option.unwrap();
-    HttpResponseBuilder::new(StatusCode::OK).json(&body)
+            model.updated_at = Utc::now();
+        })?
+        .ok_or_else(|| ApiError::not_found("list", &id))
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(&model))
 }
 
 #[derive(Deserialize)]
 struct PutListRequestData {
     name: String,
 }
+impl Validate for PutListRequestData {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.name("name", &mut self.name);
+        validator.finish()
+    }
+}
 async fn put_list(
+    Authenticated { user, .. }: Authenticated,
     body: web::Json<PutListRequestData>,
-    db: web::Data<Database>,
+    db: web::Data<dyn Store>,
     id: web::Path<String>,
-) -> impl Responder {
-    let list_collection_mutex = db.get_list_collection();
-    let mut list_collection = list_collection_mutex.lock().unwrap();
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
-    let body = body.into_inner();
-    let save_result = list_collection.put_one(
-        |model| model.id == id.clone(),
-        List {
-            id: id.clone(),
-            name: body.name.clone(),
-        },
-    );
-    if let Err(e) = save_result {
-        return HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR).json(e.to_string());
-    }
-    let body = save_result.unwrap();
-    HttpResponseBuilder::new(StatusCode::OK).json(&body)
+    let mut body = body.into_inner();
+    body.validate()?;
+    let now = Utc::now();
+    let model = blocking::write(&db, move |db| {
+        // an existing list needs an editor, a missing one is created for the caller
+        if db.find_list(&id)?.is_some() {
+            find_list_as(db, &id, &user, Role::Editor)?;
+        }
+        Ok(db.put_list(List {
+            id,
+            name: body.name,
+            owner_id: Some(user.id),
+            created_at: now,
+            updated_at: now,
+        })?)
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(&model))
 }
 
-async fn delete_list(id: web::Path<String>, db: web::Data<Database>) -> impl Responder {
+#[derive(Deserialize)]
+struct ReorderEntriesRequestData {
+    /// the ids of all entries of the list in their new order
+    ids: Vec<String>,
+}
+impl Validate for ReorderEntriesRequestData {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        for id in &self.ids {
+            validator.id("ids", id);
+        }
+        let unique: HashSet<&String> = self.ids.iter().collect();
+        if unique.len() != self.ids.len() {
+            validator.error("ids", "duplicate", "must not contain an id more than once");
+        }
+        validator.finish()
+    }
+}
+async fn reorder_entries(
+    Authenticated { user, .. }: Authenticated,
+    body: web::Json<ReorderEntriesRequestData>,
+    db: web::Data<dyn Store>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
     let id = id.into_inner();
+    let mut body = body.into_inner();
+    body.validate()?;
+    let entries = blocking::write(&db, move |db| {
+        find_list_as(db, &id, &user, Role::Editor)?;
+        let entries = db.find_entries_of_list(&id)?;
+        let is_complete = entries.len() == body.ids.len()
+            && entries.iter().all(|entry| body.ids.contains(&entry.id));
+        if !is_complete {
+            let mut validator = Validator::new();
+            validator.error(
+                "ids",
+                "incomplete",
+                "must contain the id of every entry of the list and nothing else",
+            );
+            validator.finish()?;
+        }
+        Ok(db.reorder_entries(&id, &body.ids)?)
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(entries))
+}
 
-    let entry_collection_mutex = db.get_entry_collection();
-    let mut entry_collection = entry_collection_mutex.lock().unwrap();
-    let delete_result = entry_collection.delete_many(|model| {
-        // this does not compare the pointers like in C, but the actual values
-        // the pointers are pointing to
-        &model.list_id == &id
-    });
-    if let Err(e) = delete_result {
-        return HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR).json(e.to_string());
-    }
+async fn delete_list(
+    Authenticated { user, .. }: Authenticated,
+    id: web::Path<String>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
 
-    let list_collection_mutex = db.get_list_collection();
-    let mut list_collection = list_collection_mutex.lock().unwrap();
-    let delete_result = list_collection.delete_one(|model| &model.id == &id);
-    if let Err(e) = delete_result {
-        return HttpResponseBuilder::new(StatusCode::INTERNAL_SERVER_ERROR).json(e.to_string());
-    }
-    let model_option = delete_result.unwrap();
-    if model_option.is_none() {
-        return HttpResponseBuilder::new(StatusCode::NOT_FOUND).json("not found");
-    }
-    HttpResponseBuilder::new(StatusCode::NO_CONTENT).finish()
+    // this also deletes all entries of the list
+    blocking::write(&db, move |db| {
+        find_list_as(db, &id, &user, Role::Owner)?;
+        db.delete_list(&id)?
+            .ok_or_else(|| ApiError::not_found("list", &id))
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::NO_CONTENT).finish())
 }
 
 pub fn configure_routes(config: &mut ServiceConfig) {
     config.route("", web::get().to(get_lists));
     config.route("/{id}", web::get().to(get_list));
     config.route("/{id}/entries", web::get().to(get_list_and_its_entries));
+    config.route("/{id}/entries/reorder", web::post().to(reorder_entries));
+    config.route("/{id}/tree", web::get().to(get_list_tree));
     config.route("", web::post().to(post_list));
     config.route("/{id}", web::patch().to(patch_list));
     config.route("/{id}", web::put().to(put_list));
@@ -0,0 +1,169 @@
+use actix_web::{
+    http::StatusCode,
+    web::{self, ServiceConfig},
+    HttpResponse, HttpResponseBuilder,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::membership::{Membership, Role},
+    storage::Store,
+};
+
+use super::{
+    auth::Authenticated,
+    blocking,
+    error::ApiError,
+    list::find_list_as,
+    validation::{Validate, Validator},
+};
+
+/// A membership together with the name of the user it gives access to.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MemberResponseData {
+    user_id: String,
+    name: String,
+    role: Role,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+fn member_response_data(
+    db: &dyn Store,
+    membership: Membership,
+) -> Result<MemberResponseData, ApiError> {
+    let user = db
+        .find_user(&membership.user_id)?
+        .ok_or_else(|| ApiError::not_found("user", &membership.user_id))?;
+    Ok(MemberResponseData {
+        user_id: membership.user_id,
+        name: user.name,
+        role: membership.role,
+        created_at: membership.created_at,
+        updated_at: membership.updated_at,
+    })
+}
+
+/// Responds with the users the list is shared with, its owner is not one of them.
+async fn get_members(
+    Authenticated { user, .. }: Authenticated,
+    list_id: web::Path<String>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let list_id = list_id.into_inner();
+    let members = blocking::read(&db, move |db| {
+        find_list_as(db, &list_id, &user, Role::Viewer)?;
+        db.find_memberships_of_list(&list_id)?
+            .into_iter()
+            .map(|membership| member_response_data(db, membership))
+            .collect::<Result<Vec<_>, _>>()
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(members))
+}
+
+#[derive(Deserialize)]
+struct PostMemberRequestData {
+    /// name of the user to share the list with
+    name: String,
+    role: Role,
+}
+impl Validate for PostMemberRequestData {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.name("name", &mut self.name);
+        validator.finish()
+    }
+}
+/// Shares the list with the user of the given name.
+async fn post_member(
+    Authenticated { user, .. }: Authenticated,
+    list_id: web::Path<String>,
+    body: web::Json<PostMemberRequestData>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let list_id = list_id.into_inner();
+    let mut request_data = body.into_inner();
+    request_data.validate()?;
+    let member = blocking::write(&db, move |db| {
+        let list = find_list_as(db, &list_id, &user, Role::Owner)?;
+        let invited = db
+            .find_user_by_name(&request_data.name)?
+            .ok_or_else(|| ApiError::not_found("user", &request_data.name))?;
+        // the user who created the list owns it already
+        if list.owner_id.as_ref() == Some(&invited.id) {
+            return Err(ApiError::conflict("member", "userId", &invited.id));
+        }
+        let now = Utc::now();
+        let membership = Membership {
+            list_id,
+            user_id: invited.id,
+            role: request_data.role,
+            created_at: now,
+            updated_at: now,
+        };
+        if !db.append_membership(membership.clone())? {
+            return Err(ApiError::conflict("member", "userId", &membership.user_id));
+        }
+        member_response_data(db, membership)
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::CREATED).json(member))
+}
+
+#[derive(Deserialize)]
+struct PatchMemberRequestData {
+    role: Role,
+}
+async fn patch_member(
+    Authenticated { user, .. }: Authenticated,
+    path: web::Path<(String, String)>,
+    body: web::Json<PatchMemberRequestData>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let (list_id, user_id) = path.into_inner();
+    let body = body.into_inner();
+    let member = blocking::write(&db, move |db| {
+        find_list_as(db, &list_id, &user, Role::Owner)?;
+        let membership = db
+            .patch_membership(&list_id, &user_id, &mut |membership| {
+                membership.role = body.role;
+                membership.updated_at = Utc::now();
+            })?
+            .ok_or_else(|| ApiError::not_found("member", &user_id))?;
+        member_response_data(db, membership)
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(member))
+}
+
+/// Revokes the user's access to the list, members may also leave a list on their own.
+async fn delete_member(
+    Authenticated { user, .. }: Authenticated,
+    path: web::Path<(String, String)>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let (list_id, user_id) = path.into_inner();
+    blocking::write(&db, move |db| {
+        let required = if user_id == user.id {
+            Role::Viewer
+        } else {
+            Role::Owner
+        };
+        find_list_as(db, &list_id, &user, required)?;
+        db.delete_membership(&list_id, &user_id)?
+            .ok_or_else(|| ApiError::not_found("member", &user_id))
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::NO_CONTENT).finish())
+}
+
+/// The routes below a list, `{list_id}` is part of the scope they are configured on.
+pub fn configure_routes(config: &mut ServiceConfig) {
+    config.route("", web::get().to(get_members));
+    config.route("", web::post().to(post_member));
+    config.route("/{user_id}", web::patch().to(patch_member));
+    config.route("/{user_id}", web::delete().to(delete_member));
+}
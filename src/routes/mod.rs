@@ -1,2 +1,10 @@
+pub mod auth;
+pub mod blocking;
 pub mod entry;
+pub mod error;
 pub mod list;
+pub mod member;
+pub mod pagination;
+pub mod search;
+pub mod tag;
+pub mod validation;
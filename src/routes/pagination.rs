@@ -0,0 +1,58 @@
+use actix_web::{http::StatusCode, HttpRequest, HttpResponse, HttpResponseBuilder};
+use serde::Serialize;
+
+use crate::storage::query::{Page, Paginated};
+
+use super::validation::Validator;
+
+pub const MAX_LIMIT: usize = 1000;
+
+/// Checks the `limit` query parameter.
+pub fn validate_page(validator: &mut Validator, limit: Option<usize>) {
+    if let Some(limit) = limit {
+        validator.range("limit", limit, 1, MAX_LIMIT);
+    }
+}
+
+/// Responds with the items of the page as a json array.
+/// The number of all matching items is sent in the `X-Total-Count` header and,
+/// if the result is limited, the `Link` header points to the first, previous,
+/// next and last page.
+pub fn paginated_response<T>(
+    request: &HttpRequest,
+    page: Page,
+    result: Paginated<T>,
+) -> HttpResponse
+where
+    T: Serialize,
+{
+    let mut response = HttpResponseBuilder::new(StatusCode::OK);
+    response.insert_header(("X-Total-Count", result.total.to_string()));
+    if let Some(limit) = page.limit {
+        let mut links = vec![page_link(request, 0, limit, "first")];
+        if page.offset > 0 {
+            let previous_offset = page.offset.saturating_sub(limit);
+            links.push(page_link(request, previous_offset, limit, "prev"));
+        }
+        // the offset comes from the client and may be close to `usize::MAX`
+        let next_offset = page.offset.saturating_add(limit);
+        if next_offset < result.total {
+            links.push(page_link(request, next_offset, limit, "next"));
+        }
+        let last_offset = result.total.saturating_sub(1) / limit * limit;
+        links.push(page_link(request, last_offset, limit, "last"));
+        response.insert_header(("Link", links.join(", ")));
+    }
+    response.json(result.items)
+}
+
+/// Links to the same path with the same query, except for `offset` and `limit`.
+fn page_link(request: &HttpRequest, offset: usize, limit: usize, rel: &str) -> String {
+    let mut query: Vec<(String, String)> =
+        serde_urlencoded::from_str(request.query_string()).unwrap_or_default();
+    query.retain(|(key, _)| key != "offset" && key != "limit");
+    query.push(("limit".to_string(), limit.to_string()));
+    query.push(("offset".to_string(), offset.to_string()));
+    let query = serde_urlencoded::to_string(query).unwrap_or_default();
+    format!("<{}?{}>; rel=\"{}\"", request.path(), query, rel)
+}
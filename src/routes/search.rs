@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use actix_web::{
+    web::{self, ServiceConfig},
+    HttpRequest, HttpResponse,
+};
+use serde::Deserialize;
+
+use crate::{
+    models::{entry::Entry, list::List, parent_and_children::ParentAndChildren},
+    storage::{
+        query::{EntryFilter, ListFilter, Page, Paginated, Sort},
+        Store,
+    },
+};
+
+use super::{
+    auth::Authenticated,
+    blocking,
+    error::ApiError,
+    pagination::{paginated_response, validate_page},
+    validation::{Validate, Validator},
+};
+
+/// How well a name matches the search term, higher is better.
+/// `term` has to be lowercase already.
+fn rank(name: &str, term: &str) -> Option<u8> {
+    let name = name.to_lowercase();
+    if name == term {
+        Some(3)
+    } else if name.starts_with(term) {
+        Some(2)
+    } else if name.contains(term) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+struct SearchGroup {
+    list: List,
+    score: u8,
+    entries: Vec<(u8, Entry)>,
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    q: String,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+impl Validate for SearchQuery {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.name("q", &mut self.q);
+        validate_page(&mut validator, self.limit);
+        validator.finish()
+    }
+}
+
+/// Finds the lists the user has access to and their entries whose name contains
+/// the search term, ignoring case.
+/// Matching entries are grouped by their list, a list whose name does not match
+/// is still included if any of its entries do. Groups and the entries within them
+/// are ordered by how well they match: exact names first, then names starting
+/// with the term, then the rest. `limit` and `offset` page the groups.
+async fn search(
+    Authenticated { user, .. }: Authenticated,
+    request: HttpRequest,
+    query: web::Query<SearchQuery>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let mut query = query.into_inner();
+    query.validate()?;
+    let term = query.q.to_lowercase();
+    let page = Page {
+        limit: query.limit,
+        offset: query.offset.unwrap_or(0),
+    };
+
+    let groups = blocking::read(&db, move |db| {
+        let lists = db.query_lists(
+            &ListFilter {
+                member_id: Some(user.id.clone()),
+                name_contains: Some(term.clone()),
+                ..Default::default()
+            },
+            Sort::default(),
+            Page::default(),
+        )?;
+        let entries = db.query_entries(
+            &EntryFilter {
+                name_contains: Some(term.clone()),
+                member_id: Some(user.id),
+                ..Default::default()
+            },
+            Sort::default(),
+            Page::default(),
+        )?;
+
+        let mut groups: HashMap<String, SearchGroup> = HashMap::new();
+        for list in lists.items {
+            let score = rank(&list.name, &term).unwrap_or(0);
+            groups.insert(
+                list.id.clone(),
+                SearchGroup {
+                    list,
+                    score,
+                    entries: Vec::new(),
+                },
+            );
+        }
+        for entry in entries.items {
+            let score = rank(&entry.name, &term).unwrap_or(0);
+            if !groups.contains_key(&entry.list_id) {
+                let Some(list) = db.find_list(&entry.list_id)? else {
+                    continue;
+                };
+                groups.insert(
+                    entry.list_id.clone(),
+                    SearchGroup {
+                        list,
+                        score: 0,
+                        entries: Vec::new(),
+                    },
+                );
+            }
+            let group = groups.get_mut(&entry.list_id).unwrap();
+            group.score = group.score.max(score);
+            group.entries.push((score, entry));
+        }
+        Ok(groups)
+    })
+    .await?;
+
+    let mut groups: Vec<SearchGroup> = groups.into_values().collect();
+    groups.sort_by_cached_key(|group| (u8::MAX - group.score, group.list.name.to_lowercase()));
+    let total = groups.len();
+    let groups: Vec<(List, Vec<Entry>)> = groups
+        .into_iter()
+        .skip(page.offset)
+        .take(page.limit.unwrap_or(usize::MAX))
+        .map(|mut group| {
+            // stable, so equally ranked entries keep their creation order
+            group.entries.sort_by_key(|(score, _)| u8::MAX - score);
+            let entries = group.entries.into_iter().map(|(_, entry)| entry).collect();
+            (group.list, entries)
+        })
+        .collect();
+
+    let items: Vec<ParentAndChildren<&List, Entry>> = groups
+        .iter()
+        .map(|(list, entries)| ParentAndChildren {
+            parent: list,
+            children: entries,
+        })
+        .collect();
+    Ok(paginated_response(
+        &request,
+        page,
+        Paginated { items, total },
+    ))
+}
+
+pub fn configure_routes(config: &mut ServiceConfig) {
+    config.route("", web::get().to(search));
+}
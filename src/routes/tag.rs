@@ -0,0 +1,206 @@
+use actix_web::{
+    http::StatusCode,
+    web::{self, ServiceConfig},
+    HttpRequest, HttpResponse, HttpResponseBuilder,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    models::{tag::Tag, user::User},
+    prototype_db::WriteError,
+    storage::{
+        query::{Page, Sort, SortKey, SortOrder},
+        Store, StoreError,
+    },
+};
+
+use super::{
+    auth::Authenticated,
+    blocking,
+    error::ApiError,
+    pagination::{paginated_response, validate_page},
+    validation::{Validate, Validator},
+};
+
+/// Returns the tag if the user owns it, tags of other users respond like missing ones.
+pub(super) fn find_own_tag(db: &dyn Store, id: &str, user: &User) -> Result<Tag, ApiError> {
+    db.find_tag(id)?
+        .filter(|tag| tag.owner_id.as_deref() == Some(user.id.as_str()))
+        .ok_or_else(|| ApiError::not_found("tag", id))
+}
+
+/// Tag names are unique among the tags of a user ignoring case, `id` is the tag that may
+/// already have the name.
+/// The stores' unique index on the name still rejects requests that race past this check.
+fn check_name_is_free(
+    db: &dyn Store,
+    user: &User,
+    name: &str,
+    id: Option<&str>,
+) -> Result<(), ApiError> {
+    match db.find_tag_by_name(&user.id, name)? {
+        Some(tag) if Some(tag.id.as_str()) != id => Err(ApiError::conflict("tag", "name", name)),
+        _ => Ok(()),
+    }
+}
+
+/// The index key of a taken name includes the owner, the problem only names the name.
+fn name_conflict(name: &str) -> impl FnOnce(StoreError) -> ApiError + '_ {
+    move |e| match e {
+        StoreError::Write(WriteError::Duplicate { .. }) => ApiError::conflict("tag", "name", name),
+        e => e.into(),
+    }
+}
+
+#[derive(Deserialize)]
+struct GetTagsQuery {
+    limit: Option<usize>,
+    offset: Option<usize>,
+    sort: Option<SortKey>,
+    order: Option<SortOrder>,
+}
+impl Validate for GetTagsQuery {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validate_page(&mut validator, self.limit);
+        if matches!(self.sort, Some(SortKey::Due | SortKey::Position)) {
+            validator.error(
+                "sort",
+                "unsupported",
+                "tags can only be sorted by name, created or updated",
+            );
+        }
+        validator.finish()
+    }
+}
+async fn get_tags(
+    Authenticated { user, .. }: Authenticated,
+    request: HttpRequest,
+    query: web::Query<GetTagsQuery>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let mut query = query.into_inner();
+    query.validate()?;
+    let sort = Sort {
+        key: query.sort.unwrap_or_default(),
+        order: query.order.unwrap_or_default(),
+    };
+    let page = Page {
+        limit: query.limit,
+        offset: query.offset.unwrap_or(0),
+    };
+    let tags = blocking::read(&db, move |db| Ok(db.query_tags(&user.id, sort, page)?)).await?;
+    Ok(paginated_response(&request, page, tags))
+}
+
+async fn get_tag(
+    Authenticated { user, .. }: Authenticated,
+    id: web::Path<String>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let tag = blocking::read(&db, move |db| find_own_tag(db, &id, &user)).await?;
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(tag))
+}
+
+#[derive(Deserialize)]
+struct PostTagRequestData {
+    name: String,
+}
+impl Validate for PostTagRequestData {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.name("name", &mut self.name);
+        validator.finish()
+    }
+}
+async fn post_tag(
+    Authenticated { user, .. }: Authenticated,
+    body: web::Json<PostTagRequestData>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let mut request_data = body.into_inner();
+    request_data.validate()?;
+    let now = Utc::now();
+    let new_model = Tag {
+        id: Uuid::new_v4().to_string(),
+        owner_id: Some(user.id.clone()),
+        name: request_data.name,
+        created_at: now,
+        updated_at: now,
+    };
+    let model = new_model.clone();
+    blocking::write(&db, move |db| {
+        let name = model.name.clone();
+        check_name_is_free(db, &user, &name, None)?;
+        db.append_tag(model).map_err(name_conflict(&name))
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::CREATED).json(&new_model))
+}
+
+#[derive(Deserialize)]
+struct PatchTagRequestData {
+    name: Option<String>,
+}
+impl Validate for PatchTagRequestData {
+    fn validate(&mut self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.optional_name("name", &mut self.name);
+        validator.finish()
+    }
+}
+async fn patch_tag(
+    Authenticated { user, .. }: Authenticated,
+    body: web::Json<PatchTagRequestData>,
+    db: web::Data<dyn Store>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+    let mut body = body.into_inner();
+    body.validate()?;
+    let model = blocking::write(&db, move |db| {
+        find_own_tag(db, &id, &user)?;
+        if let Some(name) = &body.name {
+            check_name_is_free(db, &user, name, Some(&id))?;
+        }
+        let name = body.name.clone().unwrap_or_default();
+        db.patch_tag(&id, &mut |model| {
+            if let Some(name) = &body.name {
+                model.name = name.clone();
+            }
+            model.updated_at = Utc::now();
+        })
+        .map_err(name_conflict(&name))?
+        .ok_or_else(|| ApiError::not_found("tag", &id))
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::OK).json(&model))
+}
+
+async fn delete_tag(
+    Authenticated { user, .. }: Authenticated,
+    id: web::Path<String>,
+    db: web::Data<dyn Store>,
+) -> Result<HttpResponse, ApiError> {
+    let id = id.into_inner();
+
+    // this also removes the tag from all entries
+    blocking::write(&db, move |db| {
+        find_own_tag(db, &id, &user)?;
+        db.delete_tag(&id)?
+            .ok_or_else(|| ApiError::not_found("tag", &id))
+    })
+    .await?;
+    Ok(HttpResponseBuilder::new(StatusCode::NO_CONTENT).finish())
+}
+
+pub fn configure_routes(config: &mut ServiceConfig) {
+    config.route("", web::get().to(get_tags));
+    config.route("/{id}", web::get().to(get_tag));
+    config.route("", web::post().to(post_tag));
+    config.route("/{id}", web::patch().to(patch_tag));
+    config.route("/{id}", web::delete().to(delete_tag));
+}
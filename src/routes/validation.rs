@@ -0,0 +1,150 @@
+use serde::Serialize;
+
+use crate::models::recurrence::{Frequency, Recurrence};
+
+use super::error::ApiError;
+
+pub const MAX_NAME_LENGTH: usize = 200;
+pub const MAX_ID_LENGTH: usize = 64;
+pub const MAX_NOTES_LENGTH: usize = 10_000;
+pub const MAX_RECURRENCE_INTERVAL: usize = 1000;
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+pub const MAX_PASSWORD_LENGTH: usize = 1024;
+
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Request bodies check and normalize themselves before a handler uses them.
+pub trait Validate {
+    fn validate(&mut self) -> Result<(), ApiError>;
+}
+
+/// Collects the errors of all fields, so a client learns about all of them at once.
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn error(&mut self, field: &'static str, code: &'static str, message: impl Into<String>) {
+        self.errors.push(FieldError {
+            field,
+            code,
+            message: message.into(),
+        });
+    }
+
+    /// Trims the name and checks that it is neither empty nor too long.
+    pub fn name(&mut self, field: &'static str, value: &mut String) {
+        let trimmed = value.trim();
+        if trimmed.len() != value.len() {
+            *value = trimmed.to_string();
+        }
+        if value.is_empty() {
+            self.error(field, "empty", "must not be empty or only whitespace");
+        } else if value.chars().count() > MAX_NAME_LENGTH {
+            self.error(
+                field,
+                "too_long",
+                format!("must be at most {} characters long", MAX_NAME_LENGTH),
+            );
+        }
+    }
+
+    pub fn optional_name(&mut self, field: &'static str, value: &mut Option<String>) {
+        if let Some(value) = value {
+            self.name(field, value);
+        }
+    }
+
+    pub fn id(&mut self, field: &'static str, value: &str) {
+        if value.trim().is_empty() {
+            self.error(field, "empty", "must not be empty or only whitespace");
+        } else if value.len() > MAX_ID_LENGTH {
+            self.error(
+                field,
+                "too_long",
+                format!("must be at most {} characters long", MAX_ID_LENGTH),
+            );
+        }
+    }
+
+    pub fn optional_id(&mut self, field: &'static str, value: &Option<String>) {
+        if let Some(value) = value {
+            self.id(field, value);
+        }
+    }
+
+    /// Passwords are taken as they are, whitespace included.
+    pub fn password(&mut self, field: &'static str, value: &str) {
+        let length = value.chars().count();
+        if length < MIN_PASSWORD_LENGTH {
+            self.error(
+                field,
+                "too_short",
+                format!("must be at least {} characters long", MIN_PASSWORD_LENGTH),
+            );
+        } else if length > MAX_PASSWORD_LENGTH {
+            self.error(
+                field,
+                "too_long",
+                format!("must be at most {} characters long", MAX_PASSWORD_LENGTH),
+            );
+        }
+    }
+
+    pub fn notes(&mut self, field: &'static str, value: &str) {
+        if value.chars().count() > MAX_NOTES_LENGTH {
+            self.error(
+                field,
+                "too_long",
+                format!("must be at most {} characters long", MAX_NOTES_LENGTH),
+            );
+        }
+    }
+
+    pub fn recurrence(&mut self, value: &Recurrence) {
+        self.range(
+            "recurrence.interval",
+            value.interval as usize,
+            1,
+            MAX_RECURRENCE_INTERVAL,
+        );
+        if !value.weekdays.is_empty() && value.frequency != Frequency::Weekly {
+            self.error(
+                "recurrence.weekdays",
+                "unsupported",
+                "only weekly rules can recur on given weekdays",
+            );
+        }
+        if value.count == Some(0) {
+            self.error("recurrence.count", "out_of_range", "must be at least 1");
+        }
+    }
+
+    pub fn range(&mut self, field: &'static str, value: usize, min: usize, max: usize) {
+        if value < min || value > max {
+            self.error(
+                field,
+                "out_of_range",
+                format!("must be between {} and {}", min, max),
+            );
+        }
+    }
+
+    pub fn finish(self) -> Result<(), ApiError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ApiError::Validation(self.errors))
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Entries can have sub-entries, which refer to their parent entry in the same list.
+
+use std::collections::HashSet;
+
+use crate::models::entry::Entry;
+
+/// Returns the ids of all entries below the entry with the given id, in the order of `entries`.
+pub fn descendant_ids(entries: &[&Entry], id: &str) -> Vec<String> {
+    let mut ids = HashSet::from([id]);
+    // a child can come before its parent, so this repeats until nothing is added
+    loop {
+        let found = ids.len();
+        for entry in entries {
+            if let Some(parent_id) = &entry.parent_id {
+                if ids.contains(parent_id.as_str()) {
+                    ids.insert(&entry.id);
+                }
+            }
+        }
+        if ids.len() == found {
+            break;
+        }
+    }
+    entries
+        .iter()
+        .filter(|entry| entry.id != id && ids.contains(entry.id.as_str()))
+        .map(|entry| entry.id.clone())
+        .collect()
+}
@@ -0,0 +1,319 @@
+use std::{fmt, fs, io, path::Path, str::FromStr, sync::Arc};
+
+use serde::Deserialize;
+
+use crate::{
+    models::{
+        entry::Entry,
+        list::List,
+        membership::Membership,
+        tag::Tag,
+        user::{Session, User},
+    },
+    prototype_db::{Database, JournalOptions, LoadError, WriteError},
+};
+
+pub mod hierarchy;
+pub mod position;
+pub mod prototype;
+pub mod query;
+pub mod sqlite;
+
+use query::{EntryFilter, ListFilter, Page, Paginated, Sort};
+
+#[derive(Debug)]
+pub enum StoreError {
+    Io(io::Error),
+    Sqlite(rusqlite::Error),
+    Load(LoadError),
+    Write(WriteError),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Io(e) => write!(f, "{}", e),
+            StoreError::Sqlite(e) => write!(f, "{}", e),
+            StoreError::Load(e) => write!(f, "{}", e),
+            StoreError::Write(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> Self {
+        StoreError::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        StoreError::Sqlite(e)
+    }
+}
+
+impl From<LoadError> for StoreError {
+    fn from(e: LoadError) -> Self {
+        StoreError::Load(e)
+    }
+}
+
+impl From<WriteError> for StoreError {
+    fn from(e: WriteError) -> Self {
+        StoreError::Write(e)
+    }
+}
+
+/// Storage of lists, independent of how and where they are persisted.
+pub trait ListStore {
+    fn query_lists(
+        &self,
+        filter: &ListFilter,
+        sort: Sort,
+        page: Page,
+    ) -> Result<Paginated<List>, StoreError>;
+
+    fn find_list(&self, id: &str) -> Result<Option<List>, StoreError>;
+
+    fn append_list(&self, list: List) -> Result<(), StoreError>;
+
+    /// Applies `update` to the list with the given id and returns the updated list,
+    /// or `None` if there is no such list.
+    fn patch_list(
+        &self,
+        id: &str,
+        update: &mut dyn FnMut(&mut List),
+    ) -> Result<Option<List>, StoreError>;
+
+    /// Replaces the list with the same id or creates it if it does not exist yet.
+    /// A replaced list keeps its owner and its `created_at`.
+    fn put_list(&self, list: List) -> Result<List, StoreError>;
+
+    /// Deletes the list, all of its entries and its memberships.
+    fn delete_list(&self, id: &str) -> Result<Option<List>, StoreError>;
+
+    /// Makes the user the owner of all lists that have none and returns how many there were.
+    fn claim_ownerless_lists(&self, owner_id: &str) -> Result<usize, StoreError>;
+}
+
+/// Storage of entries, independent of how and where they are persisted.
+pub trait EntryStore {
+    fn query_entries(
+        &self,
+        filter: &EntryFilter,
+        sort: Sort,
+        page: Page,
+    ) -> Result<Paginated<Entry>, StoreError>;
+
+    fn find_entry(&self, id: &str) -> Result<Option<Entry>, StoreError>;
+
+    /// Returns the entries of the list ordered by their position.
+    fn find_entries_of_list(&self, list_id: &str) -> Result<Vec<Entry>, StoreError>;
+
+    /// Adds the entry after the last entry of its list and returns it with its position.
+    /// Fails with `WriteError::Missing` if the list does not exist.
+    fn append_entry(&self, entry: Entry) -> Result<Entry, StoreError>;
+
+    /// Applies `update` to the entry with the given id and returns the updated entry,
+    /// or `None` if there is no such entry.
+    /// An entry that is moved to another list is added after the last entry of that list
+    /// and its sub-entries move along. Fails with `WriteError::Missing` if that list
+    /// does not exist.
+    fn patch_entry(
+        &self,
+        id: &str,
+        update: &mut dyn FnMut(&mut Entry),
+    ) -> Result<Option<Entry>, StoreError>;
+
+    /// Like `patch_entry`, and in the same transaction adds the entry `follow_up` returns
+    /// for the entry before and after the update. It is added after the last entry of its
+    /// list with the tags of the updated entry, unless an entry with its id exists already.
+    fn patch_entry_with_follow_up(
+        &self,
+        id: &str,
+        update: &mut dyn FnMut(&mut Entry),
+        follow_up: &mut dyn FnMut(&Entry, &Entry) -> Option<Entry>,
+    ) -> Result<Option<Entry>, StoreError>;
+
+    /// Replaces the entry with the same id or creates it if it does not exist yet,
+    /// returns it together with whether it was created.
+    /// A replaced entry keeps its `created_at` and its position, unless it is moved to
+    /// another list like with `patch_entry`. A created entry is added after the last entry
+    /// of its list. Fails with `WriteError::Missing` if the list does not exist.
+    fn put_entry(&self, entry: Entry) -> Result<(Entry, bool), StoreError>;
+
+    /// Moves the entry to `index` among the entries of its list and returns it,
+    /// or `None` if there is no such entry. An index past the end moves it to the end.
+    fn move_entry(&self, id: &str, index: usize) -> Result<Option<Entry>, StoreError>;
+
+    /// Orders the entries of the list like `ids` and returns them in their new order.
+    /// Entries that are not in `ids` are moved behind the others.
+    fn reorder_entries(&self, list_id: &str, ids: &[String]) -> Result<Vec<Entry>, StoreError>;
+
+    /// Deletes the entry and all of its sub-entries.
+    fn delete_entry(&self, id: &str) -> Result<Option<Entry>, StoreError>;
+}
+
+/// Storage of tags and of the entries they are assigned to.
+pub trait TagStore {
+    /// Returns the tags the user owns.
+    fn query_tags(
+        &self,
+        owner_id: &str,
+        sort: Sort,
+        page: Page,
+    ) -> Result<Paginated<Tag>, StoreError>;
+
+    fn find_tag(&self, id: &str) -> Result<Option<Tag>, StoreError>;
+
+    /// Returns the user's tag with the given name, ignoring case.
+    fn find_tag_by_name(&self, owner_id: &str, name: &str) -> Result<Option<Tag>, StoreError>;
+
+    /// Fails with `WriteError::Duplicate` if the owner has a tag with the name, ignoring case.
+    fn append_tag(&self, tag: Tag) -> Result<(), StoreError>;
+
+    /// Applies `update` to the tag with the given id and returns the updated tag,
+    /// or `None` if there is no such tag.
+    fn patch_tag(
+        &self,
+        id: &str,
+        update: &mut dyn FnMut(&mut Tag),
+    ) -> Result<Option<Tag>, StoreError>;
+
+    /// Deletes the tag and removes it from all entries.
+    fn delete_tag(&self, id: &str) -> Result<Option<Tag>, StoreError>;
+
+    /// Makes the user the owner of the tags that have none and returns how many there were.
+    /// Tags with a name the user already has, ignoring case, stay without an owner.
+    fn claim_ownerless_tags(&self, owner_id: &str) -> Result<usize, StoreError>;
+
+    /// Returns the tags assigned to the entry, ordered by name.
+    fn find_tags_of_entry(&self, entry_id: &str) -> Result<Vec<Tag>, StoreError>;
+
+    /// Assigns the tag to the entry and returns whether it was not assigned before.
+    /// Fails with `WriteError::Missing` if the entry or the tag does not exist.
+    fn tag_entry(&self, entry_id: &str, tag_id: &str) -> Result<bool, StoreError>;
+
+    /// Removes the tag from the entry and returns whether it was assigned.
+    fn untag_entry(&self, entry_id: &str, tag_id: &str) -> Result<bool, StoreError>;
+}
+
+/// Storage of the users who have access to lists they don't own.
+pub trait MembershipStore {
+    fn find_membership(
+        &self,
+        list_id: &str,
+        user_id: &str,
+    ) -> Result<Option<Membership>, StoreError>;
+
+    /// Returns the memberships of the list in the order they were added.
+    fn find_memberships_of_list(&self, list_id: &str) -> Result<Vec<Membership>, StoreError>;
+
+    /// Adds the membership and returns whether the user was not a member of the list before,
+    /// an existing membership is left as it is.
+    fn append_membership(&self, membership: Membership) -> Result<bool, StoreError>;
+
+    /// Applies `update` to the membership of the user in the list and returns it,
+    /// or `None` if there is no such membership.
+    fn patch_membership(
+        &self,
+        list_id: &str,
+        user_id: &str,
+        update: &mut dyn FnMut(&mut Membership),
+    ) -> Result<Option<Membership>, StoreError>;
+
+    fn delete_membership(
+        &self,
+        list_id: &str,
+        user_id: &str,
+    ) -> Result<Option<Membership>, StoreError>;
+}
+
+/// Storage of users and of the sessions they signed in with.
+pub trait UserStore {
+    fn find_user(&self, id: &str) -> Result<Option<User>, StoreError>;
+
+    /// Returns the user with the given name, ignoring case.
+    fn find_user_by_name(&self, name: &str) -> Result<Option<User>, StoreError>;
+
+    /// Fails with `WriteError::Duplicate` if another user has the name, ignoring case.
+    fn append_user(&self, user: User) -> Result<(), StoreError>;
+
+    /// Returns the session with the token hash, even if it has expired.
+    fn find_session(&self, token_hash: &str) -> Result<Option<Session>, StoreError>;
+
+    fn append_session(&self, session: Session) -> Result<(), StoreError>;
+
+    /// Deletes the session and returns whether it existed.
+    fn delete_session(&self, token_hash: &str) -> Result<bool, StoreError>;
+}
+
+/// Everything the routes need from a storage backend.
+/// Handlers receive it as `web::Data<dyn Store>`.
+pub trait Store:
+    ListStore + EntryStore + TagStore + MembershipStore + UserStore + Send + Sync
+{
+}
+
+impl<T> Store for T where
+    T: ListStore + EntryStore + TagStore + MembershipStore + UserStore + Send + Sync
+{
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// one pretty printed json file per collection, see `prototype_db`
+    Json,
+    /// a single sqlite database file
+    Sqlite,
+}
+
+impl FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(StorageBackend::Json),
+            "sqlite" => Ok(StorageBackend::Sqlite),
+            _ => Err(format!(
+                "unknown storage backend \"{}\", expected \"json\" or \"sqlite\"",
+                s
+            )),
+        }
+    }
+}
+
+/// Opens the store of the given backend, all of its files live inside `dir`.
+/// The journal options only apply to the json backend.
+pub fn open(
+    backend: StorageBackend,
+    dir: &str,
+    journal_options: Option<JournalOptions>,
+) -> Result<Arc<dyn Store>, StoreError> {
+    let store: Arc<dyn Store> = match backend {
+        StorageBackend::Json => Arc::new(Database::new(dir.to_string(), journal_options)?),
+        StorageBackend::Sqlite => {
+            fs::create_dir_all(dir)?;
+            let path = Path::new(dir).join("todo.sqlite3");
+            Arc::new(sqlite::SqliteStore::open(&path.display().to_string())?)
+        }
+    };
+    Ok(store)
+}
+
+/// Makes the user with the given name the owner of the lists and tags from before there
+/// were users. Returns how many lists and tags they got, or `None` if there is no such user.
+pub fn claim_ownerless(
+    store: &dyn Store,
+    user_name: &str,
+) -> Result<Option<(usize, usize)>, StoreError> {
+    let Some(user) = store.find_user_by_name(user_name)? else {
+        return Ok(None);
+    };
+    let lists = store.claim_ownerless_lists(&user.id)?;
+    let tags = store.claim_ownerless_tags(&user.id)?;
+    Ok(Some((lists, tags)))
+}
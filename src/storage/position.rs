@@ -0,0 +1,65 @@
+//! Entries are ordered by their `position` within their list. Positions are spaced
+//! apart, so moving an entry usually only changes the position of that one entry.
+
+use crate::models::entry::Entry;
+
+pub const POSITION_GAP: i64 = 1024;
+
+/// Returns the position of an entry that is added after the one at `last`.
+pub fn position_after(last: Option<i64>) -> i64 {
+    last.map_or(POSITION_GAP, |last| last + POSITION_GAP)
+}
+
+/// Returns the new positions of the entries that have to change so that the entry
+/// with the given id ends up at `index`. `entries` are all entries of its list in order.
+pub fn move_to(entries: &[&Entry], id: &str, index: usize) -> Vec<(String, i64)> {
+    let Some(moved) = entries.iter().find(|entry| entry.id == id) else {
+        return Vec::new();
+    };
+    let mut others: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| entry.id != id)
+        .copied()
+        .collect();
+    let index = index.min(others.len());
+    let before = index.checked_sub(1).map(|index| others[index].position);
+    let after = others.get(index).map(|entry| entry.position);
+    let position = match (before, after) {
+        (None, None) => Some(POSITION_GAP),
+        (Some(before), None) => Some(before + POSITION_GAP),
+        (None, Some(after)) => Some(after - POSITION_GAP),
+        (Some(before), Some(after)) => (after - before > 1).then(|| before + (after - before) / 2),
+    };
+    match position {
+        Some(position) if position == moved.position => Vec::new(),
+        Some(position) => vec![(moved.id.clone(), position)],
+        // the neighbours leave no room, so the whole list is spaced out again
+        None => {
+            others.insert(index, moved);
+            renumber(&others)
+        }
+    }
+}
+
+/// Orders the entries like `ids`, the entries that are not in `ids` follow in their current order.
+pub fn in_order_of<'a>(mut entries: Vec<&'a Entry>, ids: &[String]) -> Vec<&'a Entry> {
+    entries.sort_by_key(|entry| {
+        ids.iter()
+            .position(|id| id == &entry.id)
+            .unwrap_or(ids.len())
+    });
+    entries
+}
+
+/// Returns the new positions of the entries that have to change
+/// to space the entries evenly in the given order.
+pub fn renumber(ordered: &[&Entry]) -> Vec<(String, i64)> {
+    ordered
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            let position = (index as i64 + 1) * POSITION_GAP;
+            (entry.position != position).then(|| (entry.id.clone(), position))
+        })
+        .collect()
+}
@@ -0,0 +1,633 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+
+use crate::{
+    models::{
+        entry::Entry,
+        list::List,
+        membership::Membership,
+        tag::{EntryTag, Tag},
+        user::{Session, User},
+    },
+    prototype_db::{
+        tag_name_key, Collection, Database, WriteError, ID_INDEX, LIST_ID_INDEX, NAME_INDEX,
+        OWNER_ID_INDEX, USER_ID_INDEX,
+    },
+};
+
+use super::{
+    hierarchy,
+    position::{self, in_order_of, position_after},
+    query::{sort_and_paginate, EntryFilter, ListFilter, Page, Paginated, Sort},
+    EntryStore, ListStore, MembershipStore, StoreError, TagStore, UserStore,
+};
+
+/// Fails with `WriteError::Missing` if the data that `id` refers to was not found.
+fn require<T>(found: Option<T>, collection: &'static str, id: &str) -> Result<T, WriteError> {
+    found.ok_or_else(|| WriteError::Missing {
+        collection,
+        id: id.to_string(),
+    })
+}
+
+/// Returns the entries of the list ordered by their position,
+/// entries with the same position keep the order they are stored in.
+fn entries_of_list<'a>(entry_collection: &'a Collection<Entry>, list_id: &str) -> Vec<&'a Entry> {
+    let mut entries = entry_collection.find_by_key(LIST_ID_INDEX, list_id);
+    entries.sort_by_key(|entry| entry.position);
+    entries
+}
+
+fn last_position(entry_collection: &Collection<Entry>, list_id: &str) -> Option<i64> {
+    entry_collection
+        .find_by_key(LIST_ID_INDEX, list_id)
+        .into_iter()
+        .map(|entry| entry.position)
+        .max()
+}
+
+fn tagged_entry_ids(database: &Database, tag_id: &str) -> HashSet<String> {
+    let entry_tag_collection = database.get_entry_tag_collection().read();
+    entry_tag_collection
+        .find(|model| model.tag_id == tag_id)
+        .into_iter()
+        .map(|entry_tag| entry_tag.entry_id.clone())
+        .collect()
+}
+
+/// Returns the ids of the lists the user owns or is a member of.
+fn member_list_ids(database: &Database, user_id: &str) -> HashSet<String> {
+    let mut list_ids: HashSet<String> = {
+        let membership_collection = database.get_membership_collection().read();
+        membership_collection
+            .find_by_key(USER_ID_INDEX, user_id)
+            .into_iter()
+            .map(|membership| membership.list_id.clone())
+            .collect()
+    };
+    let list_collection = database.get_list_collection().read();
+    list_ids.extend(
+        list_collection
+            .find_by_key(OWNER_ID_INDEX, user_id)
+            .into_iter()
+            .map(|list| list.id.clone()),
+    );
+    list_ids
+}
+
+/// Removes all tags from the deleted entries.
+fn untag_entries(
+    entry_tag_collection: &mut Collection<EntryTag>,
+    entry_ids: &HashSet<String>,
+) -> Result<(), StoreError> {
+    entry_tag_collection.delete_many(|model| entry_ids.contains(&model.entry_id))?;
+    Ok(())
+}
+
+/// Writes the new positions of the moved entries in a single operation.
+fn apply_positions(
+    entry_collection: &mut Collection<Entry>,
+    changes: Vec<(String, i64)>,
+) -> Result<(), StoreError> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let now = Utc::now();
+    let positions: HashMap<String, i64> = changes.into_iter().collect();
+    entry_collection.patch_many_by_keys(
+        ID_INDEX,
+        positions.keys().map(String::as_str),
+        |model| {
+            model.position = positions[&model.id];
+            model.updated_at = now;
+        },
+    )?;
+    Ok(())
+}
+
+/// Writes the changed entry that was in the list `list_id` before.
+/// If it was moved to another list it is added after the last entry of that list
+/// and its sub-entries move along.
+fn replace_entry(
+    entry_collection: &mut Collection<Entry>,
+    list_id: &str,
+    mut entry: Entry,
+) -> Result<Entry, StoreError> {
+    let id = entry.id.clone();
+    if entry.list_id == list_id {
+        return Ok(entry_collection.put_one_by_key(ID_INDEX, &id, entry)?);
+    }
+
+    entry.position = position_after(last_position(entry_collection, &entry.list_id));
+    let mut positions = HashMap::new();
+    let mut position = entry.position;
+    let entries = entries_of_list(entry_collection, list_id);
+    for descendant_id in hierarchy::descendant_ids(&entries, &id) {
+        position = position_after(Some(position));
+        positions.insert(descendant_id, position);
+    }
+    let ids = positions.keys().chain([&id]).map(String::as_str);
+    entry_collection.patch_many_by_keys(ID_INDEX, ids, |model| {
+        if model.id == id {
+            *model = entry.clone();
+        } else {
+            model.list_id = entry.list_id.clone();
+            model.position = positions[&model.id];
+            model.updated_at = entry.updated_at;
+        }
+    })?;
+    Ok(entry)
+}
+
+impl ListStore for Database {
+    fn query_lists(
+        &self,
+        filter: &ListFilter,
+        sort: Sort,
+        page: Page,
+    ) -> Result<Paginated<List>, StoreError> {
+        let member_list_ids = filter
+            .member_id
+            .as_ref()
+            .map(|member_id| member_list_ids(self, member_id));
+        let list_collection = self.get_list_collection().read();
+        let lists = match &member_list_ids {
+            // in storage order, which `sort_and_paginate` relies on for ties
+            Some(ids) => {
+                list_collection.find(|model| ids.contains(&model.id) && filter.matches(model))
+            }
+            None => list_collection.find(|model| filter.matches(model)),
+        };
+        Ok(sort_and_paginate(lists, sort, page))
+    }
+
+    fn find_list(&self, id: &str) -> Result<Option<List>, StoreError> {
+        let list_collection = self.get_list_collection().read();
+        Ok(list_collection.find_one_by_key(ID_INDEX, id).cloned())
+    }
+
+    fn append_list(&self, list: List) -> Result<(), StoreError> {
+        self.get_list_collection()
+            .write(|list_collection| Ok(list_collection.append(list)?))
+    }
+
+    fn patch_list(
+        &self,
+        id: &str,
+        update: &mut dyn FnMut(&mut List),
+    ) -> Result<Option<List>, StoreError> {
+        self.get_list_collection()
+            .write(|list_collection| Ok(list_collection.patch_one_by_key(ID_INDEX, id, update)?))
+    }
+
+    fn put_list(&self, mut list: List) -> Result<List, StoreError> {
+        self.get_list_collection().write(|list_collection| {
+            let id = list.id.clone();
+            if let Some(existing) = list_collection.find_one_by_key(ID_INDEX, &id) {
+                list.owner_id = existing.owner_id.clone();
+                list.created_at = existing.created_at;
+            }
+            Ok(list_collection.put_one_by_key(ID_INDEX, &id, list)?)
+        })
+    }
+
+    fn delete_list(&self, id: &str) -> Result<Option<List>, StoreError> {
+        self.transaction(|transaction| {
+            let entry_ids: HashSet<String> = transaction
+                .entries
+                .find_by_key(LIST_ID_INDEX, id)
+                .into_iter()
+                .map(|entry| entry.id.clone())
+                .collect();
+            transaction
+                .entries
+                .delete_many_by_keys(LIST_ID_INDEX, [id])?;
+            untag_entries(&mut transaction.entry_tags, &entry_ids)?;
+            transaction
+                .memberships
+                .delete_many_by_keys(LIST_ID_INDEX, [id])?;
+            Ok(transaction.lists.delete_one_by_key(ID_INDEX, id)?)
+        })
+    }
+
+    fn claim_ownerless_lists(&self, owner_id: &str) -> Result<usize, StoreError> {
+        self.get_list_collection().write(|list_collection| {
+            let claimed = list_collection.patch_many_by_keys(OWNER_ID_INDEX, [""], |list| {
+                list.owner_id = Some(owner_id.to_string())
+            })?;
+            Ok(claimed.len())
+        })
+    }
+}
+
+impl EntryStore for Database {
+    fn query_entries(
+        &self,
+        filter: &EntryFilter,
+        sort: Sort,
+        page: Page,
+    ) -> Result<Paginated<Entry>, StoreError> {
+        let tagged_entry_ids = filter
+            .tag_id
+            .as_ref()
+            .map(|tag_id| tagged_entry_ids(self, tag_id));
+        let member_list_ids = filter
+            .member_id
+            .as_ref()
+            .map(|member_id| member_list_ids(self, member_id));
+        let entry_collection = self.get_entry_collection().read();
+        let matches = |model: &Entry| {
+            filter.matches(model)
+                && tagged_entry_ids
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&model.id))
+                && member_list_ids
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&model.list_id))
+        };
+        let entries = match &filter.list_id {
+            Some(list_id) => entry_collection
+                .find_by_key(LIST_ID_INDEX, list_id)
+                .into_iter()
+                .filter(|model| matches(model))
+                .collect(),
+            None => entry_collection.find(matches),
+        };
+        Ok(sort_and_paginate(entries, sort, page))
+    }
+
+    fn find_entry(&self, id: &str) -> Result<Option<Entry>, StoreError> {
+        let entry_collection = self.get_entry_collection().read();
+        Ok(entry_collection.find_one_by_key(ID_INDEX, id).cloned())
+    }
+
+    fn find_entries_of_list(&self, list_id: &str) -> Result<Vec<Entry>, StoreError> {
+        let entry_collection = self.get_entry_collection().read();
+        Ok(entries_of_list(&entry_collection, list_id)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    fn append_entry(&self, mut entry: Entry) -> Result<Entry, StoreError> {
+        // the list can't be deleted in between, which would leave the entry behind
+        self.transaction(|transaction| {
+            let list = transaction.lists.find_one_by_key(ID_INDEX, &entry.list_id);
+            require(list, "list", &entry.list_id)?;
+            let entry_collection = &mut transaction.entries;
+            entry.position = position_after(last_position(entry_collection, &entry.list_id));
+            entry_collection.append(entry.clone())?;
+            Ok(entry)
+        })
+    }
+
+    fn patch_entry(
+        &self,
+        id: &str,
+        update: &mut dyn FnMut(&mut Entry),
+    ) -> Result<Option<Entry>, StoreError> {
+        self.patch_entry_with_follow_up(id, update, &mut |_, _| None)
+    }
+
+    fn patch_entry_with_follow_up(
+        &self,
+        id: &str,
+        update: &mut dyn FnMut(&mut Entry),
+        follow_up: &mut dyn FnMut(&Entry, &Entry) -> Option<Entry>,
+    ) -> Result<Option<Entry>, StoreError> {
+        self.transaction(|transaction| {
+            let entry_collection = &mut transaction.entries;
+            let Some(old) = entry_collection.find_one_by_key(ID_INDEX, id).cloned() else {
+                return Ok(None);
+            };
+            let mut entry = old.clone();
+            update(&mut entry);
+            if entry.list_id != old.list_id {
+                let list = transaction.lists.find_one_by_key(ID_INDEX, &entry.list_id);
+                require(list, "list", &entry.list_id)?;
+            }
+            let entry = replace_entry(&mut transaction.entries, &old.list_id, entry)?;
+
+            let Some(mut next) = follow_up(&old, &entry) else {
+                return Ok(Some(entry));
+            };
+            let entry_collection = &mut transaction.entries;
+            if entry_collection
+                .find_one_by_key(ID_INDEX, &next.id)
+                .is_some()
+            {
+                return Ok(Some(entry));
+            }
+            let list = transaction.lists.find_one_by_key(ID_INDEX, &next.list_id);
+            require(list, "list", &next.list_id)?;
+            next.position = position_after(last_position(entry_collection, &next.list_id));
+            let next_id = next.id.clone();
+            entry_collection.append(next)?;
+            let tag_ids: Vec<String> = transaction
+                .entry_tags
+                .find(|model| model.entry_id == id)
+                .into_iter()
+                .map(|entry_tag| entry_tag.tag_id.clone())
+                .collect();
+            for tag_id in tag_ids {
+                transaction.entry_tags.append(EntryTag {
+                    entry_id: next_id.clone(),
+                    tag_id,
+                })?;
+            }
+            Ok(Some(entry))
+        })
+    }
+
+    fn put_entry(&self, mut entry: Entry) -> Result<(Entry, bool), StoreError> {
+        self.transaction(|transaction| {
+            let list = transaction.lists.find_one_by_key(ID_INDEX, &entry.list_id);
+            require(list, "list", &entry.list_id)?;
+            let entry_collection = &mut transaction.entries;
+            let id = entry.id.clone();
+            let Some(existing) = entry_collection.find_one_by_key(ID_INDEX, &id).cloned() else {
+                entry.position = position_after(last_position(entry_collection, &entry.list_id));
+                return Ok((entry_collection.put_one_by_key(ID_INDEX, &id, entry)?, true));
+            };
+            entry.created_at = existing.created_at;
+            entry.position = existing.position;
+            Ok((
+                replace_entry(entry_collection, &existing.list_id, entry)?,
+                false,
+            ))
+        })
+    }
+
+    fn move_entry(&self, id: &str, index: usize) -> Result<Option<Entry>, StoreError> {
+        self.get_entry_collection().write(|entry_collection| {
+            let Some(entry) = entry_collection.find_one_by_key(ID_INDEX, id) else {
+                return Ok(None);
+            };
+            let entries = entries_of_list(entry_collection, &entry.list_id);
+            let changes = position::move_to(&entries, id, index);
+            apply_positions(entry_collection, changes)?;
+            Ok(entry_collection.find_one_by_key(ID_INDEX, id).cloned())
+        })
+    }
+
+    fn reorder_entries(&self, list_id: &str, ids: &[String]) -> Result<Vec<Entry>, StoreError> {
+        self.get_entry_collection().write(|entry_collection| {
+            let entries = entries_of_list(entry_collection, list_id);
+            let changes = position::renumber(&in_order_of(entries, ids));
+            apply_positions(entry_collection, changes)?;
+            Ok(entries_of_list(entry_collection, list_id)
+                .into_iter()
+                .cloned()
+                .collect())
+        })
+    }
+
+    fn delete_entry(&self, id: &str) -> Result<Option<Entry>, StoreError> {
+        self.transaction(|transaction| {
+            let entry_collection = &mut transaction.entries;
+            let Some(entry) = entry_collection.find_one_by_key(ID_INDEX, id).cloned() else {
+                return Ok(None);
+            };
+            let entries = entries_of_list(entry_collection, &entry.list_id);
+            let mut ids: HashSet<String> = hierarchy::descendant_ids(&entries, id)
+                .into_iter()
+                .collect();
+            ids.insert(entry.id.clone());
+            entry_collection.delete_many_by_keys(ID_INDEX, ids.iter().map(String::as_str))?;
+            untag_entries(&mut transaction.entry_tags, &ids)?;
+            Ok(Some(entry))
+        })
+    }
+}
+
+impl TagStore for Database {
+    fn query_tags(
+        &self,
+        owner_id: &str,
+        sort: Sort,
+        page: Page,
+    ) -> Result<Paginated<Tag>, StoreError> {
+        let tag_collection = self.get_tag_collection().read();
+        let tags = tag_collection.find_by_key(OWNER_ID_INDEX, owner_id);
+        Ok(sort_and_paginate(tags, sort, page))
+    }
+
+    fn find_tag(&self, id: &str) -> Result<Option<Tag>, StoreError> {
+        let tag_collection = self.get_tag_collection().read();
+        Ok(tag_collection.find_one_by_key(ID_INDEX, id).cloned())
+    }
+
+    fn find_tag_by_name(&self, owner_id: &str, name: &str) -> Result<Option<Tag>, StoreError> {
+        let tag_collection = self.get_tag_collection().read();
+        Ok(tag_collection
+            .find_one_by_key(NAME_INDEX, &tag_name_key(Some(owner_id), name))
+            .cloned())
+    }
+
+    fn append_tag(&self, tag: Tag) -> Result<(), StoreError> {
+        self.get_tag_collection()
+            .write(|tag_collection| Ok(tag_collection.append(tag)?))
+    }
+
+    fn patch_tag(
+        &self,
+        id: &str,
+        update: &mut dyn FnMut(&mut Tag),
+    ) -> Result<Option<Tag>, StoreError> {
+        self.get_tag_collection()
+            .write(|tag_collection| Ok(tag_collection.patch_one_by_key(ID_INDEX, id, update)?))
+    }
+
+    fn delete_tag(&self, id: &str) -> Result<Option<Tag>, StoreError> {
+        self.transaction(|transaction| {
+            let tag_option = transaction.tags.delete_one_by_key(ID_INDEX, id)?;
+            if tag_option.is_some() {
+                transaction
+                    .entry_tags
+                    .delete_many(|model| model.tag_id == id)?;
+            }
+            Ok(tag_option)
+        })
+    }
+
+    fn claim_ownerless_tags(&self, owner_id: &str) -> Result<usize, StoreError> {
+        self.get_tag_collection().write(|tag_collection| {
+            // the first of the tags with a name that is free gets it, like the unique index does
+            let mut names: HashSet<String> = tag_collection
+                .find_by_key(OWNER_ID_INDEX, owner_id)
+                .into_iter()
+                .map(|tag| tag.name.to_lowercase())
+                .collect();
+            let ids: Vec<String> = tag_collection
+                .find_by_key(OWNER_ID_INDEX, "")
+                .into_iter()
+                .filter(|tag| names.insert(tag.name.to_lowercase()))
+                .map(|tag| tag.id.clone())
+                .collect();
+            let claimed = tag_collection.patch_many_by_keys(
+                ID_INDEX,
+                ids.iter().map(String::as_str),
+                |tag| tag.owner_id = Some(owner_id.to_string()),
+            )?;
+            Ok(claimed.len())
+        })
+    }
+
+    fn find_tags_of_entry(&self, entry_id: &str) -> Result<Vec<Tag>, StoreError> {
+        let tag_ids: HashSet<String> = {
+            let entry_tag_collection = self.get_entry_tag_collection().read();
+            entry_tag_collection
+                .find(|model| model.entry_id == entry_id)
+                .into_iter()
+                .map(|entry_tag| entry_tag.tag_id.clone())
+                .collect()
+        };
+        let tag_collection = self.get_tag_collection().read();
+        let mut tags: Vec<Tag> = tag_collection
+            .find(|model| tag_ids.contains(&model.id))
+            .into_iter()
+            .cloned()
+            .collect();
+        tags.sort_by_cached_key(|tag| tag.name.to_lowercase());
+        Ok(tags)
+    }
+
+    fn tag_entry(&self, entry_id: &str, tag_id: &str) -> Result<bool, StoreError> {
+        // neither can be deleted in between, which would leave the assignment behind
+        self.transaction(|transaction| {
+            let entry = transaction.entries.find_one_by_key(ID_INDEX, entry_id);
+            require(entry, "entry", entry_id)?;
+            let tag = transaction.tags.find_one_by_key(ID_INDEX, tag_id);
+            require(tag, "tag", tag_id)?;
+            let entry_tag_collection = &mut transaction.entry_tags;
+            let is_assigned = entry_tag_collection
+                .find_one(|model| model.entry_id == entry_id && model.tag_id == tag_id)
+                .is_some();
+            if !is_assigned {
+                entry_tag_collection.append(EntryTag {
+                    entry_id: entry_id.to_string(),
+                    tag_id: tag_id.to_string(),
+                })?;
+            }
+            Ok(!is_assigned)
+        })
+    }
+
+    fn untag_entry(&self, entry_id: &str, tag_id: &str) -> Result<bool, StoreError> {
+        self.get_entry_tag_collection()
+            .write(|entry_tag_collection| {
+                Ok(entry_tag_collection
+                    .delete_one(|model| model.entry_id == entry_id && model.tag_id == tag_id)?
+                    .is_some())
+            })
+    }
+}
+
+impl MembershipStore for Database {
+    fn find_membership(
+        &self,
+        list_id: &str,
+        user_id: &str,
+    ) -> Result<Option<Membership>, StoreError> {
+        let membership_collection = self.get_membership_collection().read();
+        Ok(membership_collection
+            .find_by_key(LIST_ID_INDEX, list_id)
+            .into_iter()
+            .find(|model| model.user_id == user_id)
+            .cloned())
+    }
+
+    fn find_memberships_of_list(&self, list_id: &str) -> Result<Vec<Membership>, StoreError> {
+        let membership_collection = self.get_membership_collection().read();
+        Ok(membership_collection
+            .find_by_key(LIST_ID_INDEX, list_id)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+
+    fn append_membership(&self, membership: Membership) -> Result<bool, StoreError> {
+        self.get_membership_collection()
+            .write(|membership_collection| {
+                let is_member = membership_collection
+                    .find_by_key(LIST_ID_INDEX, &membership.list_id)
+                    .into_iter()
+                    .any(|model| model.user_id == membership.user_id);
+                if !is_member {
+                    membership_collection.append(membership)?;
+                }
+                Ok(!is_member)
+            })
+    }
+
+    fn patch_membership(
+        &self,
+        list_id: &str,
+        user_id: &str,
+        update: &mut dyn FnMut(&mut Membership),
+    ) -> Result<Option<Membership>, StoreError> {
+        self.get_membership_collection()
+            .write(|membership_collection| {
+                Ok(membership_collection.patch_one(
+                    |model| model.list_id == list_id && model.user_id == user_id,
+                    update,
+                )?)
+            })
+    }
+
+    fn delete_membership(
+        &self,
+        list_id: &str,
+        user_id: &str,
+    ) -> Result<Option<Membership>, StoreError> {
+        self.get_membership_collection()
+            .write(|membership_collection| {
+                Ok(membership_collection
+                    .delete_one(|model| model.list_id == list_id && model.user_id == user_id)?)
+            })
+    }
+}
+
+impl UserStore for Database {
+    fn find_user(&self, id: &str) -> Result<Option<User>, StoreError> {
+        let user_collection = self.get_user_collection().read();
+        Ok(user_collection.find_one_by_key(ID_INDEX, id).cloned())
+    }
+
+    fn find_user_by_name(&self, name: &str) -> Result<Option<User>, StoreError> {
+        let user_collection = self.get_user_collection().read();
+        Ok(user_collection
+            .find_one_by_key(NAME_INDEX, &name.to_lowercase())
+            .cloned())
+    }
+
+    fn append_user(&self, user: User) -> Result<(), StoreError> {
+        self.get_user_collection()
+            .write(|user_collection| Ok(user_collection.append(user)?))
+    }
+
+    fn find_session(&self, token_hash: &str) -> Result<Option<Session>, StoreError> {
+        let session_collection = self.get_session_collection().read();
+        Ok(session_collection
+            .find_one_by_key(ID_INDEX, token_hash)
+            .cloned())
+    }
+
+    fn append_session(&self, session: Session) -> Result<(), StoreError> {
+        // expired sessions are dropped whenever someone signs in
+        let now = session.created_at;
+        self.get_session_collection().write(|session_collection| {
+            session_collection.delete_many(|model| model.expires_at <= now)?;
+            Ok(session_collection.append(session)?)
+        })
+    }
+
+    fn delete_session(&self, token_hash: &str) -> Result<bool, StoreError> {
+        self.get_session_collection().write(|session_collection| {
+            Ok(session_collection
+                .delete_one_by_key(ID_INDEX, token_hash)?
+                .is_some())
+        })
+    }
+}
@@ -0,0 +1,225 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::models::{entry::Entry, list::List, tag::Tag};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Name,
+    #[default]
+    Created,
+    Updated,
+    /// only entries have a due date, entries without one come first
+    Due,
+    /// only entries have a position, it is only meaningful within a list
+    Position,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sort {
+    pub key: SortKey,
+    pub order: SortOrder,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Page {
+    /// `None` returns everything after `offset`
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    /// number of matching items, regardless of the page
+    pub total: usize,
+}
+
+/// Whether `name` contains `term`, ignoring case.
+pub fn name_contains(name: &str, term: &str) -> bool {
+    name.to_lowercase().contains(&term.to_lowercase())
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ListFilter {
+    /// matches lists the user owns or is a member of,
+    /// this is not checked by `matches` because lists don't know their members
+    pub member_id: Option<String>,
+    /// matches lists whose name contains this, ignoring case
+    pub name_contains: Option<String>,
+    /// matches lists that were changed at or after this time
+    pub updated_since: Option<DateTime<Utc>>,
+}
+
+impl ListFilter {
+    pub fn matches(&self, list: &List) -> bool {
+        if let Some(term) = &self.name_contains {
+            if !name_contains(&list.name, term) {
+                return false;
+            }
+        }
+        if let Some(updated_since) = self.updated_since {
+            if list.updated_at < updated_since {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EntryFilter {
+    pub list_id: Option<String>,
+    pub done: Option<bool>,
+    /// matches entries whose name contains this, ignoring case
+    pub name_contains: Option<String>,
+    /// matches entries that were changed at or after this time
+    pub updated_since: Option<DateTime<Utc>>,
+    /// matches entries that are due at or after this time
+    pub due_from: Option<DateTime<Utc>>,
+    /// matches entries that are due before this time
+    pub due_before: Option<DateTime<Utc>>,
+    /// matches entries the tag is assigned to,
+    /// this is not checked by `matches` because entries don't know their tags
+    pub tag_id: Option<String>,
+    /// matches entries of lists the user owns or is a member of,
+    /// this is not checked by `matches` because entries don't know who has access to their list
+    pub member_id: Option<String>,
+}
+
+impl EntryFilter {
+    pub fn matches(&self, entry: &Entry) -> bool {
+        if let Some(list_id) = &self.list_id {
+            if &entry.list_id != list_id {
+                return false;
+            }
+        }
+        if let Some(done) = self.done {
+            if entry.done != done {
+                return false;
+            }
+        }
+        if let Some(term) = &self.name_contains {
+            if !name_contains(&entry.name, term) {
+                return false;
+            }
+        }
+        if let Some(updated_since) = self.updated_since {
+            if entry.updated_at < updated_since {
+                return false;
+            }
+        }
+        if self.due_from.is_some() || self.due_before.is_some() {
+            let Some(due_at) = entry.due_at else {
+                return false;
+            };
+            if self.due_from.is_some_and(|due_from| due_at < due_from)
+                || self
+                    .due_before
+                    .is_some_and(|due_before| due_at >= due_before)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// What models can be sorted by.
+pub trait Sortable {
+    fn name(&self) -> &str;
+    fn created_at(&self) -> DateTime<Utc>;
+    fn updated_at(&self) -> DateTime<Utc>;
+
+    fn due_at(&self) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    fn position(&self) -> i64 {
+        0
+    }
+}
+
+impl Sortable for List {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+}
+
+impl Sortable for Entry {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+
+    fn due_at(&self) -> Option<DateTime<Utc>> {
+        self.due_at
+    }
+
+    fn position(&self) -> i64 {
+        self.position
+    }
+}
+
+impl Sortable for Tag {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn created_at(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+}
+
+/// Sorts and pages items that are given in the order they were stored in.
+/// Sorting is stable, so items with the same key keep their storage order.
+pub fn sort_and_paginate<T>(mut items: Vec<&T>, sort: Sort, page: Page) -> Paginated<T>
+where
+    T: Sortable + Clone,
+{
+    match sort.key {
+        SortKey::Name => items.sort_by_cached_key(|item| item.name().to_lowercase()),
+        SortKey::Created => items.sort_by_key(|item| item.created_at()),
+        SortKey::Updated => items.sort_by_key(|item| item.updated_at()),
+        SortKey::Due => items.sort_by_key(|item| item.due_at()),
+        SortKey::Position => items.sort_by_key(|item| item.position()),
+    }
+    if sort.order == SortOrder::Desc {
+        items.reverse();
+    }
+    let total = items.len();
+    let items = items
+        .into_iter()
+        .skip(page.offset)
+        .take(page.limit.unwrap_or(usize::MAX))
+        .cloned()
+        .collect();
+    Paginated { items, total }
+}
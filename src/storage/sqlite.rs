@@ -0,0 +1,1261 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex, MutexGuard,
+};
+
+use chrono::Utc;
+
+use rusqlite::{
+    functions::FunctionFlags,
+    params, params_from_iter,
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    Connection, ErrorCode, OptionalExtension, Row, ToSql,
+};
+
+use crate::{
+    models::{
+        entry::{Entry, Priority},
+        list::List,
+        membership::{Membership, Role},
+        recurrence::Recurrence,
+        tag::Tag,
+        user::{Session, User},
+    },
+    prototype_db::{tag_name_key, WriteError, NAME_INDEX},
+};
+
+use super::{
+    position::{self, in_order_of, position_after},
+    query::{EntryFilter, ListFilter, Page, Paginated, Sort, SortKey, SortOrder},
+    EntryStore, ListStore, MembershipStore, StoreError, TagStore, UserStore,
+};
+
+// every migration is applied exactly once and in order, the number of applied
+// migrations is tracked in sqlite's user_version pragma
+// never edit a migration that has already been released, append a new one instead
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE list (
+        id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL
+    );
+    CREATE TABLE entry (
+        id TEXT PRIMARY KEY NOT NULL,
+        list_id TEXT NOT NULL REFERENCES list(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        done INTEGER NOT NULL
+    );
+    CREATE INDEX entry_list_id ON entry(list_id);
+"#,
+    r#"
+    ALTER TABLE list ADD COLUMN created_at TEXT NOT NULL DEFAULT '1970-01-01 00:00:00+00:00';
+    ALTER TABLE list ADD COLUMN updated_at TEXT NOT NULL DEFAULT '1970-01-01 00:00:00+00:00';
+    ALTER TABLE entry ADD COLUMN created_at TEXT NOT NULL DEFAULT '1970-01-01 00:00:00+00:00';
+    ALTER TABLE entry ADD COLUMN updated_at TEXT NOT NULL DEFAULT '1970-01-01 00:00:00+00:00';
+"#,
+    r#"
+    ALTER TABLE entry ADD COLUMN due_at TEXT;
+    ALTER TABLE entry ADD COLUMN priority TEXT;
+    ALTER TABLE entry ADD COLUMN notes TEXT;
+    CREATE INDEX entry_due_at ON entry(due_at);
+"#,
+    r#"
+    ALTER TABLE entry ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+    DROP INDEX entry_list_id;
+    CREATE INDEX entry_list_id_position ON entry(list_id, position);
+"#,
+    r#"
+    ALTER TABLE entry ADD COLUMN parent_id TEXT REFERENCES entry(id) ON DELETE CASCADE;
+    CREATE INDEX entry_parent_id ON entry(parent_id);
+"#,
+    r#"
+    CREATE TABLE tag (
+        id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE entry_tag (
+        entry_id TEXT NOT NULL REFERENCES entry(id) ON DELETE CASCADE,
+        tag_id TEXT NOT NULL REFERENCES tag(id) ON DELETE CASCADE,
+        PRIMARY KEY (entry_id, tag_id)
+    );
+    CREATE INDEX entry_tag_tag_id ON entry_tag(tag_id);
+"#,
+    r#"
+    ALTER TABLE entry ADD COLUMN recurrence TEXT;
+"#,
+    r#"
+    CREATE TABLE user (
+        id TEXT PRIMARY KEY NOT NULL,
+        name TEXT NOT NULL,
+        password_hash TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE UNIQUE INDEX user_name ON user(name COLLATE NOCASE);
+    CREATE TABLE session (
+        token TEXT PRIMARY KEY NOT NULL,
+        user_id TEXT NOT NULL REFERENCES user(id) ON DELETE CASCADE,
+        created_at TEXT NOT NULL,
+        expires_at TEXT NOT NULL
+    );
+    CREATE INDEX session_user_id ON session(user_id);
+"#,
+    r#"
+    ALTER TABLE list ADD COLUMN owner_id TEXT REFERENCES user(id) ON DELETE CASCADE;
+    CREATE INDEX list_owner_id ON list(owner_id);
+"#,
+    r#"
+    CREATE TABLE membership (
+        list_id TEXT NOT NULL REFERENCES list(id) ON DELETE CASCADE,
+        user_id TEXT NOT NULL REFERENCES user(id) ON DELETE CASCADE,
+        role TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        PRIMARY KEY (list_id, user_id)
+    );
+    CREATE INDEX membership_user_id ON membership(user_id);
+"#,
+    r#"
+    CREATE UNIQUE INDEX tag_name ON tag(unicode_lower(name));
+"#,
+    r#"
+    -- replaces the index the user table was created with, NOCASE only folds ascii letters
+    -- while the json backend folds all of them. That migration stays as it is,
+    -- databases that were created before this one have already applied it
+    DROP INDEX user_name;
+    CREATE UNIQUE INDEX user_name ON user(unicode_lower(name));
+"#,
+    r#"
+    -- the tokens were stored as they are, everyone has to sign in again
+    DELETE FROM session;
+    ALTER TABLE session RENAME COLUMN token TO token_hash;
+"#,
+    r#"
+    ALTER TABLE tag ADD COLUMN owner_id TEXT REFERENCES user(id) ON DELETE CASCADE;
+    DROP INDEX tag_name;
+    CREATE UNIQUE INDEX tag_owner_id_name ON tag(owner_id, unicode_lower(name));
+"#,
+    r#"
+    -- NULL owners are distinct from each other in an index, ownerless tags have to share
+    -- their names the way they do in the json backend
+    DROP INDEX tag_owner_id_name;
+    CREATE UNIQUE INDEX tag_owner_id_name ON tag(ifnull(owner_id, ''), unicode_lower(name));
+"#,
+];
+
+const LIST_COLUMNS: &str = "id, name, owner_id, created_at, updated_at";
+const TAG_COLUMNS: &str = "id, owner_id, name, created_at, updated_at";
+const USER_COLUMNS: &str = "id, name, password_hash, created_at, updated_at";
+const SESSION_COLUMNS: &str = "token_hash, user_id, created_at, expires_at";
+const MEMBERSHIP_COLUMNS: &str = "list_id, user_id, role, created_at, updated_at";
+const ENTRY_COLUMNS: &str = "id, list_id, parent_id, name, done, position, due_at, priority, \
+                             notes, recurrence, created_at, updated_at";
+
+impl ToSql for Priority {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let text = match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Urgent => "urgent",
+        };
+        Ok(text.into())
+    }
+}
+
+impl FromSql for Priority {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "low" => Ok(Priority::Low),
+            "medium" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            "urgent" => Ok(Priority::Urgent),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl ToSql for Role {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let text = match self {
+            Role::Viewer => "viewer",
+            Role::Editor => "editor",
+            Role::Owner => "owner",
+        };
+        Ok(text.into())
+    }
+}
+
+impl FromSql for Role {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value.as_str()? {
+            "viewer" => Ok(Role::Viewer),
+            "editor" => Ok(Role::Editor),
+            "owner" => Ok(Role::Owner),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+// rules are stored as json, they are only ever read and written as a whole
+impl ToSql for Recurrence {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let json = serde_json::to_string(self)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        Ok(json.into())
+    }
+}
+
+impl FromSql for Recurrence {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        serde_json::from_str(value.as_str()?).map_err(|e| FromSqlError::Other(Box::new(e)))
+    }
+}
+
+fn list_from_row(row: &Row) -> rusqlite::Result<List> {
+    Ok(List {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        owner_id: row.get("owner_id")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn tag_from_row(row: &Row) -> rusqlite::Result<Tag> {
+    Ok(Tag {
+        id: row.get("id")?,
+        owner_id: row.get("owner_id")?,
+        name: row.get("name")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn user_from_row(row: &Row) -> rusqlite::Result<User> {
+    Ok(User {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        password_hash: row.get("password_hash")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn session_from_row(row: &Row) -> rusqlite::Result<Session> {
+    Ok(Session {
+        token_hash: row.get("token_hash")?,
+        user_id: row.get("user_id")?,
+        created_at: row.get("created_at")?,
+        expires_at: row.get("expires_at")?,
+    })
+}
+
+fn membership_from_row(row: &Row) -> rusqlite::Result<Membership> {
+    Ok(Membership {
+        list_id: row.get("list_id")?,
+        user_id: row.get("user_id")?,
+        role: row.get("role")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<Entry> {
+    Ok(Entry {
+        id: row.get("id")?,
+        list_id: row.get("list_id")?,
+        parent_id: row.get("parent_id")?,
+        name: row.get("name")?,
+        done: row.get("done")?,
+        position: row.get("position")?,
+        due_at: row.get("due_at")?,
+        priority: row.get("priority")?,
+        notes: row.get("notes")?,
+        recurrence: row.get("recurrence")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+/// Reports a violated unique index or primary key like the collections of the json backend
+/// do, so both backends respond with the same conflict. Other errors are passed on.
+fn duplicate(
+    error: rusqlite::Error,
+    collection: &'static str,
+    index: &'static str,
+    key: &str,
+) -> StoreError {
+    match error {
+        rusqlite::Error::SqliteFailure(failure, _)
+            if failure.code == ErrorCode::ConstraintViolation
+                && matches!(
+                    failure.extended_code,
+                    rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE
+                        | rusqlite::ffi::SQLITE_CONSTRAINT_PRIMARYKEY
+                ) =>
+        {
+            WriteError::Duplicate {
+                collection,
+                index,
+                key: key.to_string(),
+            }
+            .into()
+        }
+        error => error.into(),
+    }
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), StoreError> {
+    let applied: usize = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", index + 1)?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
+
+/// How many connections reads are spread over.
+const READERS: usize = 4;
+
+/// Writes go through one connection and reads through a few others, so reading never waits
+/// for a write to reach the disk. In WAL mode readers see the last committed state
+/// while a write is going on.
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+    readers: Vec<Mutex<Connection>>,
+    /// the reader the next read goes through
+    next_reader: AtomicUsize,
+}
+
+impl SqliteStore {
+    pub fn open(path: &str) -> Result<Self, StoreError> {
+        let mut connection = connect(path)?;
+        connection
+            .pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+        migrate(&mut connection)?;
+        let readers = (0..READERS)
+            .map(|_| {
+                let reader = connect(path)?;
+                reader.pragma_update(None, "query_only", "ON")?;
+                Ok(Mutex::new(reader))
+            })
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            readers,
+            next_reader: AtomicUsize::new(0),
+        })
+    }
+
+    /// Hands out the readers in turn, so concurrent reads rarely wait for each other.
+    fn reader(&self) -> MutexGuard<'_, Connection> {
+        let index = self.next_reader.fetch_add(1, Ordering::Relaxed) % self.readers.len();
+        self.readers[index].lock().unwrap()
+    }
+}
+
+fn connect(path: &str) -> rusqlite::Result<Connection> {
+    let connection = Connection::open(path)?;
+    // sqlite does not enforce foreign keys unless asked to, per connection
+    connection.pragma_update(None, "foreign_keys", "ON")?;
+    // sqlite's own lower() only knows ascii, this lowercases like rust does,
+    // so both backends agree on what matches case-insensitively
+    connection.create_scalar_function(
+        "unicode_lower",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |context| Ok(context.get::<String>(0)?.to_lowercase()),
+    )?;
+    Ok(connection)
+}
+
+fn order_by(sort: Sort) -> String {
+    let direction = match sort.order {
+        SortOrder::Asc => "ASC",
+        SortOrder::Desc => "DESC",
+    };
+    match sort.key {
+        SortKey::Name => format!("unicode_lower(name) {0}, rowid {0}", direction),
+        SortKey::Created => format!("created_at {0}, rowid {0}", direction),
+        SortKey::Updated => format!("updated_at {0}, rowid {0}", direction),
+        SortKey::Due => format!("due_at {0}, rowid {0}", direction),
+        SortKey::Position => format!("position {0}, rowid {0}", direction),
+    }
+}
+
+fn where_clause(
+    conditions: Vec<&str>,
+    values: Vec<Box<dyn ToSql>>,
+) -> (String, Vec<Box<dyn ToSql>>) {
+    if conditions.is_empty() {
+        (String::new(), values)
+    } else {
+        (format!("WHERE {}", conditions.join(" AND ")), values)
+    }
+}
+
+/// Returns the WHERE clause matching the filter and the values of its placeholders.
+fn list_conditions(filter: &ListFilter) -> (String, Vec<Box<dyn ToSql>>) {
+    let mut conditions = Vec::new();
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+    if let Some(member_id) = &filter.member_id {
+        conditions
+            .push("(owner_id = ? OR id IN (SELECT list_id FROM membership WHERE user_id = ?))");
+        values.push(Box::new(member_id.clone()));
+        values.push(Box::new(member_id.clone()));
+    }
+    if let Some(term) = &filter.name_contains {
+        conditions.push("instr(unicode_lower(name), ?) > 0");
+        values.push(Box::new(term.to_lowercase()));
+    }
+    if let Some(updated_since) = filter.updated_since {
+        conditions.push("updated_at >= ?");
+        values.push(Box::new(updated_since));
+    }
+    where_clause(conditions, values)
+}
+
+/// Returns the WHERE clause matching the filter and the values of its placeholders.
+fn entry_conditions(filter: &EntryFilter) -> (String, Vec<Box<dyn ToSql>>) {
+    let mut conditions = Vec::new();
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+    if let Some(list_id) = &filter.list_id {
+        conditions.push("list_id = ?");
+        values.push(Box::new(list_id.clone()));
+    }
+    if let Some(done) = filter.done {
+        conditions.push("done = ?");
+        values.push(Box::new(done));
+    }
+    if let Some(term) = &filter.name_contains {
+        conditions.push("instr(unicode_lower(name), ?) > 0");
+        values.push(Box::new(term.to_lowercase()));
+    }
+    if let Some(updated_since) = filter.updated_since {
+        conditions.push("updated_at >= ?");
+        values.push(Box::new(updated_since));
+    }
+    if let Some(due_from) = filter.due_from {
+        conditions.push("due_at >= ?");
+        values.push(Box::new(due_from));
+    }
+    if let Some(due_before) = filter.due_before {
+        conditions.push("due_at < ?");
+        values.push(Box::new(due_before));
+    }
+    if let Some(tag_id) = &filter.tag_id {
+        conditions.push("id IN (SELECT entry_id FROM entry_tag WHERE tag_id = ?)");
+        values.push(Box::new(tag_id.clone()));
+    }
+    if let Some(member_id) = &filter.member_id {
+        conditions.push(
+            "list_id IN (SELECT id FROM list WHERE owner_id = ?
+                UNION SELECT list_id FROM membership WHERE user_id = ?)",
+        );
+        values.push(Box::new(member_id.clone()));
+        values.push(Box::new(member_id.clone()));
+    }
+    where_clause(conditions, values)
+}
+
+/// Counts and selects the page of rows of `table` that match the WHERE clause.
+fn query_page<T>(
+    connection: &Connection,
+    table: &str,
+    columns: &str,
+    (where_clause, mut values): (String, Vec<Box<dyn ToSql>>),
+    sort: Sort,
+    page: Page,
+    from_row: fn(&Row) -> rusqlite::Result<T>,
+) -> rusqlite::Result<Paginated<T>> {
+    let total: usize = connection.query_row(
+        &format!("SELECT COUNT(*) FROM {} {}", table, where_clause),
+        params_from_iter(values.iter()),
+        |row| row.get(0),
+    )?;
+    // a negative limit means no limit to sqlite
+    values.push(Box::new(page.limit.map(|limit| limit as i64).unwrap_or(-1)));
+    // an offset beyond what sqlite can count skips everything either way
+    values.push(Box::new(i64::try_from(page.offset).unwrap_or(i64::MAX)));
+    let mut statement = connection.prepare(&format!(
+        "SELECT {} FROM {} {} ORDER BY {} LIMIT ? OFFSET ?",
+        columns,
+        table,
+        where_clause,
+        order_by(sort)
+    ))?;
+    let items = statement
+        .query_map(params_from_iter(values.iter()), from_row)?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(Paginated { items, total })
+}
+
+fn find_list(connection: &Connection, id: &str) -> rusqlite::Result<Option<List>> {
+    connection
+        .query_row(
+            &format!("SELECT {} FROM list WHERE id = ?1", LIST_COLUMNS),
+            params![id],
+            list_from_row,
+        )
+        .optional()
+}
+
+fn missing(collection: &'static str, id: &str) -> StoreError {
+    WriteError::Missing {
+        collection,
+        id: id.to_string(),
+    }
+    .into()
+}
+
+/// Fails with `WriteError::Missing` if the list does not exist, the foreign key would only
+/// fail with a generic constraint violation.
+fn require_list(connection: &Connection, id: &str) -> Result<(), StoreError> {
+    match find_list(connection, id)? {
+        Some(_) => Ok(()),
+        None => Err(missing("list", id)),
+    }
+}
+
+fn find_entry(connection: &Connection, id: &str) -> rusqlite::Result<Option<Entry>> {
+    connection
+        .query_row(
+            &format!("SELECT {} FROM entry WHERE id = ?1", ENTRY_COLUMNS),
+            params![id],
+            entry_from_row,
+        )
+        .optional()
+}
+
+/// Returns the entries of the list ordered by their position,
+/// entries with the same position keep the order they were inserted in.
+fn entries_of_list(connection: &Connection, list_id: &str) -> rusqlite::Result<Vec<Entry>> {
+    let mut statement = connection.prepare(&format!(
+        "SELECT {} FROM entry WHERE list_id = ?1 ORDER BY position, rowid",
+        ENTRY_COLUMNS
+    ))?;
+    let entries = statement
+        .query_map(params![list_id], entry_from_row)?
+        .collect();
+    entries
+}
+
+fn last_position(connection: &Connection, list_id: &str) -> rusqlite::Result<Option<i64>> {
+    connection.query_row(
+        "SELECT MAX(position) FROM entry WHERE list_id = ?1",
+        params![list_id],
+        |row| row.get(0),
+    )
+}
+
+/// Returns the ids of all entries below the entry, ordered by their position.
+fn descendant_ids(connection: &Connection, id: &str) -> rusqlite::Result<Vec<String>> {
+    let mut statement = connection.prepare(
+        "WITH RECURSIVE subtree(id) AS (
+            SELECT id FROM entry WHERE parent_id = ?1
+            UNION
+            SELECT entry.id FROM entry JOIN subtree ON entry.parent_id = subtree.id
+         )
+         SELECT entry.id FROM entry JOIN subtree ON entry.id = subtree.id
+         ORDER BY entry.position, entry.rowid",
+    )?;
+    let ids = statement
+        .query_map(params![id], |row| row.get(0))?
+        .collect();
+    ids
+}
+
+fn apply_positions(connection: &Connection, changes: Vec<(String, i64)>) -> rusqlite::Result<()> {
+    let now = Utc::now();
+    let mut statement =
+        connection.prepare("UPDATE entry SET position = ?2, updated_at = ?3 WHERE id = ?1")?;
+    for (id, position) in changes {
+        statement.execute(params![id, position, now])?;
+    }
+    Ok(())
+}
+
+fn find_tag(connection: &Connection, id: &str) -> rusqlite::Result<Option<Tag>> {
+    connection
+        .query_row(
+            &format!("SELECT {} FROM tag WHERE id = ?1", TAG_COLUMNS),
+            params![id],
+            tag_from_row,
+        )
+        .optional()
+}
+
+fn upsert_list(connection: &Connection, list: &List) -> rusqlite::Result<()> {
+    // INSERT OR REPLACE would delete the old row first and thereby cascade to its entries
+    connection.execute(
+        "INSERT INTO list (id, name, owner_id, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(id) DO UPDATE SET
+            name = excluded.name,
+            owner_id = excluded.owner_id,
+            created_at = excluded.created_at,
+            updated_at = excluded.updated_at",
+        params![
+            list.id,
+            list.name,
+            list.owner_id,
+            list.created_at,
+            list.updated_at
+        ],
+    )?;
+    Ok(())
+}
+
+fn update_entry(connection: &Connection, entry: &Entry) -> rusqlite::Result<()> {
+    connection.execute(
+        "UPDATE entry SET list_id = ?2, parent_id = ?3, name = ?4, done = ?5, position = ?6,
+            due_at = ?7, priority = ?8, notes = ?9, recurrence = ?10, created_at = ?11,
+            updated_at = ?12
+         WHERE id = ?1",
+        params![
+            entry.id,
+            entry.list_id,
+            entry.parent_id,
+            entry.name,
+            entry.done,
+            entry.position,
+            entry.due_at,
+            entry.priority,
+            entry.notes,
+            entry.recurrence,
+            entry.created_at,
+            entry.updated_at
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_entry(connection: &Connection, entry: &Entry) -> rusqlite::Result<()> {
+    connection.execute(
+        "INSERT INTO entry (id, list_id, parent_id, name, done, position, due_at, priority,
+            notes, recurrence, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            entry.id,
+            entry.list_id,
+            entry.parent_id,
+            entry.name,
+            entry.done,
+            entry.position,
+            entry.due_at,
+            entry.priority,
+            entry.notes,
+            entry.recurrence,
+            entry.created_at,
+            entry.updated_at
+        ],
+    )?;
+    Ok(())
+}
+
+/// Writes the changed entry that was in the list `list_id` before.
+/// If it was moved to another list it is added after the last entry of that list
+/// and its sub-entries move along.
+fn replace_entry(
+    connection: &Connection,
+    list_id: &str,
+    mut entry: Entry,
+) -> rusqlite::Result<Entry> {
+    if entry.list_id != list_id {
+        entry.position = position_after(last_position(connection, &entry.list_id)?);
+        let mut position = entry.position;
+        for descendant_id in descendant_ids(connection, &entry.id)? {
+            position = position_after(Some(position));
+            connection.execute(
+                "UPDATE entry SET list_id = ?2, position = ?3, updated_at = ?4 WHERE id = ?1",
+                params![descendant_id, entry.list_id, position, entry.updated_at],
+            )?;
+        }
+    }
+    update_entry(connection, &entry)?;
+    Ok(entry)
+}
+
+impl ListStore for SqliteStore {
+    fn query_lists(
+        &self,
+        filter: &ListFilter,
+        sort: Sort,
+        page: Page,
+    ) -> Result<Paginated<List>, StoreError> {
+        let connection = self.reader();
+        Ok(query_page(
+            &connection,
+            "list",
+            LIST_COLUMNS,
+            list_conditions(filter),
+            sort,
+            page,
+            list_from_row,
+        )?)
+    }
+
+    fn find_list(&self, id: &str) -> Result<Option<List>, StoreError> {
+        let connection = self.reader();
+        Ok(find_list(&connection, id)?)
+    }
+
+    fn append_list(&self, list: List) -> Result<(), StoreError> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "INSERT INTO list (id, name, owner_id, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                list.id,
+                list.name,
+                list.owner_id,
+                list.created_at,
+                list.updated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn patch_list(
+        &self,
+        id: &str,
+        update: &mut dyn FnMut(&mut List),
+    ) -> Result<Option<List>, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let list_option = find_list(&transaction, id)?;
+        let Some(mut list) = list_option else {
+            return Ok(None);
+        };
+        update(&mut list);
+        upsert_list(&transaction, &list)?;
+        transaction.commit()?;
+        Ok(Some(list))
+    }
+
+    fn put_list(&self, mut list: List) -> Result<List, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        if let Some(existing) = find_list(&transaction, &list.id)? {
+            list.owner_id = existing.owner_id;
+            list.created_at = existing.created_at;
+        }
+        upsert_list(&transaction, &list)?;
+        transaction.commit()?;
+        Ok(list)
+    }
+
+    fn delete_list(&self, id: &str) -> Result<Option<List>, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let list_option = find_list(&transaction, id)?;
+        if list_option.is_some() {
+            // the entries and memberships are removed by the foreign keys' ON DELETE CASCADE
+            transaction.execute("DELETE FROM list WHERE id = ?1", params![id])?;
+        }
+        transaction.commit()?;
+        Ok(list_option)
+    }
+
+    fn claim_ownerless_lists(&self, owner_id: &str) -> Result<usize, StoreError> {
+        let connection = self.connection.lock().unwrap();
+        Ok(connection.execute(
+            "UPDATE list SET owner_id = ?1 WHERE owner_id IS NULL",
+            params![owner_id],
+        )?)
+    }
+}
+
+impl EntryStore for SqliteStore {
+    fn query_entries(
+        &self,
+        filter: &EntryFilter,
+        sort: Sort,
+        page: Page,
+    ) -> Result<Paginated<Entry>, StoreError> {
+        let connection = self.reader();
+        Ok(query_page(
+            &connection,
+            "entry",
+            ENTRY_COLUMNS,
+            entry_conditions(filter),
+            sort,
+            page,
+            entry_from_row,
+        )?)
+    }
+
+    fn find_entry(&self, id: &str) -> Result<Option<Entry>, StoreError> {
+        let connection = self.reader();
+        Ok(find_entry(&connection, id)?)
+    }
+
+    fn find_entries_of_list(&self, list_id: &str) -> Result<Vec<Entry>, StoreError> {
+        let connection = self.reader();
+        Ok(entries_of_list(&connection, list_id)?)
+    }
+
+    fn append_entry(&self, mut entry: Entry) -> Result<Entry, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        require_list(&transaction, &entry.list_id)?;
+        entry.position = position_after(last_position(&transaction, &entry.list_id)?);
+        insert_entry(&transaction, &entry)?;
+        transaction.commit()?;
+        Ok(entry)
+    }
+
+    fn patch_entry(
+        &self,
+        id: &str,
+        update: &mut dyn FnMut(&mut Entry),
+    ) -> Result<Option<Entry>, StoreError> {
+        self.patch_entry_with_follow_up(id, update, &mut |_, _| None)
+    }
+
+    fn patch_entry_with_follow_up(
+        &self,
+        id: &str,
+        update: &mut dyn FnMut(&mut Entry),
+        follow_up: &mut dyn FnMut(&Entry, &Entry) -> Option<Entry>,
+    ) -> Result<Option<Entry>, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let Some(old) = find_entry(&transaction, id)? else {
+            return Ok(None);
+        };
+        let mut entry = old.clone();
+        update(&mut entry);
+        if entry.list_id != old.list_id {
+            require_list(&transaction, &entry.list_id)?;
+        }
+        let entry = replace_entry(&transaction, &old.list_id, entry)?;
+        if let Some(mut next) = follow_up(&old, &entry) {
+            if find_entry(&transaction, &next.id)?.is_none() {
+                require_list(&transaction, &next.list_id)?;
+                next.position = position_after(last_position(&transaction, &next.list_id)?);
+                insert_entry(&transaction, &next)?;
+                transaction.execute(
+                    "INSERT INTO entry_tag (entry_id, tag_id)
+                     SELECT ?1, tag_id FROM entry_tag WHERE entry_id = ?2",
+                    params![next.id, id],
+                )?;
+            }
+        }
+        transaction.commit()?;
+        Ok(Some(entry))
+    }
+
+    fn put_entry(&self, mut entry: Entry) -> Result<(Entry, bool), StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        require_list(&transaction, &entry.list_id)?;
+        let Some(existing) = find_entry(&transaction, &entry.id)? else {
+            entry.position = position_after(last_position(&transaction, &entry.list_id)?);
+            insert_entry(&transaction, &entry)?;
+            transaction.commit()?;
+            return Ok((entry, true));
+        };
+        entry.created_at = existing.created_at;
+        entry.position = existing.position;
+        let entry = replace_entry(&transaction, &existing.list_id, entry)?;
+        transaction.commit()?;
+        Ok((entry, false))
+    }
+
+    fn move_entry(&self, id: &str, index: usize) -> Result<Option<Entry>, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let Some(entry) = find_entry(&transaction, id)? else {
+            return Ok(None);
+        };
+        let entries = entries_of_list(&transaction, &entry.list_id)?;
+        let changes = position::move_to(&entries.iter().collect::<Vec<_>>(), id, index);
+        apply_positions(&transaction, changes)?;
+        let entry = find_entry(&transaction, id)?;
+        transaction.commit()?;
+        Ok(entry)
+    }
+
+    fn reorder_entries(&self, list_id: &str, ids: &[String]) -> Result<Vec<Entry>, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let entries = entries_of_list(&transaction, list_id)?;
+        let changes = position::renumber(&in_order_of(entries.iter().collect(), ids));
+        apply_positions(&transaction, changes)?;
+        let entries = entries_of_list(&transaction, list_id)?;
+        transaction.commit()?;
+        Ok(entries)
+    }
+
+    fn delete_entry(&self, id: &str) -> Result<Option<Entry>, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let entry_option = find_entry(&transaction, id)?;
+        if entry_option.is_some() {
+            // the sub-entries are removed by the foreign key's ON DELETE CASCADE
+            transaction.execute("DELETE FROM entry WHERE id = ?1", params![id])?;
+        }
+        transaction.commit()?;
+        Ok(entry_option)
+    }
+}
+
+impl TagStore for SqliteStore {
+    fn query_tags(
+        &self,
+        owner_id: &str,
+        sort: Sort,
+        page: Page,
+    ) -> Result<Paginated<Tag>, StoreError> {
+        let connection = self.reader();
+        let conditions = vec!["owner_id = ?"];
+        let values: Vec<Box<dyn ToSql>> = vec![Box::new(owner_id.to_string())];
+        Ok(query_page(
+            &connection,
+            "tag",
+            TAG_COLUMNS,
+            where_clause(conditions, values),
+            sort,
+            page,
+            tag_from_row,
+        )?)
+    }
+
+    fn find_tag(&self, id: &str) -> Result<Option<Tag>, StoreError> {
+        let connection = self.reader();
+        Ok(find_tag(&connection, id)?)
+    }
+
+    fn find_tag_by_name(&self, owner_id: &str, name: &str) -> Result<Option<Tag>, StoreError> {
+        let connection = self.reader();
+        Ok(connection
+            .query_row(
+                &format!(
+                    "SELECT {} FROM tag WHERE owner_id = ?1 AND unicode_lower(name) = ?2",
+                    TAG_COLUMNS
+                ),
+                params![owner_id, name.to_lowercase()],
+                tag_from_row,
+            )
+            .optional()?)
+    }
+
+    fn append_tag(&self, tag: Tag) -> Result<(), StoreError> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "INSERT INTO tag (id, owner_id, name, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    tag.id,
+                    tag.owner_id,
+                    tag.name,
+                    tag.created_at,
+                    tag.updated_at
+                ],
+            )
+            .map_err(|e| {
+                duplicate(
+                    e,
+                    "tag",
+                    NAME_INDEX,
+                    &tag_name_key(tag.owner_id.as_deref(), &tag.name),
+                )
+            })?;
+        Ok(())
+    }
+
+    fn patch_tag(
+        &self,
+        id: &str,
+        update: &mut dyn FnMut(&mut Tag),
+    ) -> Result<Option<Tag>, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let tag_option = find_tag(&transaction, id)?;
+        let Some(mut tag) = tag_option else {
+            return Ok(None);
+        };
+        update(&mut tag);
+        transaction
+            .execute(
+                "UPDATE tag SET owner_id = ?2, name = ?3, created_at = ?4, updated_at = ?5
+                 WHERE id = ?1",
+                params![
+                    tag.id,
+                    tag.owner_id,
+                    tag.name,
+                    tag.created_at,
+                    tag.updated_at
+                ],
+            )
+            .map_err(|e| {
+                duplicate(
+                    e,
+                    "tag",
+                    NAME_INDEX,
+                    &tag_name_key(tag.owner_id.as_deref(), &tag.name),
+                )
+            })?;
+        transaction.commit()?;
+        Ok(Some(tag))
+    }
+
+    fn delete_tag(&self, id: &str) -> Result<Option<Tag>, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let tag_option = find_tag(&transaction, id)?;
+        if tag_option.is_some() {
+            // the assignments are removed by the foreign key's ON DELETE CASCADE
+            transaction.execute("DELETE FROM tag WHERE id = ?1", params![id])?;
+        }
+        transaction.commit()?;
+        Ok(tag_option)
+    }
+
+    fn claim_ownerless_tags(&self, owner_id: &str) -> Result<usize, StoreError> {
+        let connection = self.connection.lock().unwrap();
+        // OR IGNORE skips the tags whose name the unique index already has for the owner
+        Ok(connection.execute(
+            "UPDATE OR IGNORE tag SET owner_id = ?1 WHERE owner_id IS NULL",
+            params![owner_id],
+        )?)
+    }
+
+    fn find_tags_of_entry(&self, entry_id: &str) -> Result<Vec<Tag>, StoreError> {
+        let connection = self.reader();
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM tag
+             WHERE id IN (SELECT tag_id FROM entry_tag WHERE entry_id = ?1)
+             ORDER BY unicode_lower(name), rowid",
+            TAG_COLUMNS
+        ))?;
+        let tags = statement
+            .query_map(params![entry_id], tag_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(tags)
+    }
+
+    fn tag_entry(&self, entry_id: &str, tag_id: &str) -> Result<bool, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        if find_entry(&transaction, entry_id)?.is_none() {
+            return Err(missing("entry", entry_id));
+        }
+        if find_tag(&transaction, tag_id)?.is_none() {
+            return Err(missing("tag", tag_id));
+        }
+        let inserted = transaction.execute(
+            "INSERT OR IGNORE INTO entry_tag (entry_id, tag_id) VALUES (?1, ?2)",
+            params![entry_id, tag_id],
+        )?;
+        transaction.commit()?;
+        Ok(inserted > 0)
+    }
+
+    fn untag_entry(&self, entry_id: &str, tag_id: &str) -> Result<bool, StoreError> {
+        let connection = self.connection.lock().unwrap();
+        let deleted = connection.execute(
+            "DELETE FROM entry_tag WHERE entry_id = ?1 AND tag_id = ?2",
+            params![entry_id, tag_id],
+        )?;
+        Ok(deleted > 0)
+    }
+}
+
+fn find_membership(
+    connection: &Connection,
+    list_id: &str,
+    user_id: &str,
+) -> rusqlite::Result<Option<Membership>> {
+    connection
+        .query_row(
+            &format!(
+                "SELECT {} FROM membership WHERE list_id = ?1 AND user_id = ?2",
+                MEMBERSHIP_COLUMNS
+            ),
+            params![list_id, user_id],
+            membership_from_row,
+        )
+        .optional()
+}
+
+impl MembershipStore for SqliteStore {
+    fn find_membership(
+        &self,
+        list_id: &str,
+        user_id: &str,
+    ) -> Result<Option<Membership>, StoreError> {
+        let connection = self.reader();
+        Ok(find_membership(&connection, list_id, user_id)?)
+    }
+
+    fn find_memberships_of_list(&self, list_id: &str) -> Result<Vec<Membership>, StoreError> {
+        let connection = self.reader();
+        let mut statement = connection.prepare(&format!(
+            "SELECT {} FROM membership WHERE list_id = ?1 ORDER BY rowid",
+            MEMBERSHIP_COLUMNS
+        ))?;
+        let memberships = statement
+            .query_map(params![list_id], membership_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(memberships)
+    }
+
+    fn append_membership(&self, membership: Membership) -> Result<bool, StoreError> {
+        let connection = self.connection.lock().unwrap();
+        let inserted = connection.execute(
+            "INSERT INTO membership (list_id, user_id, role, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT(list_id, user_id) DO NOTHING",
+            params![
+                membership.list_id,
+                membership.user_id,
+                membership.role,
+                membership.created_at,
+                membership.updated_at
+            ],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    fn patch_membership(
+        &self,
+        list_id: &str,
+        user_id: &str,
+        update: &mut dyn FnMut(&mut Membership),
+    ) -> Result<Option<Membership>, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let Some(mut membership) = find_membership(&transaction, list_id, user_id)? else {
+            return Ok(None);
+        };
+        update(&mut membership);
+        transaction.execute(
+            "UPDATE membership SET role = ?3, created_at = ?4, updated_at = ?5
+             WHERE list_id = ?1 AND user_id = ?2",
+            params![
+                list_id,
+                user_id,
+                membership.role,
+                membership.created_at,
+                membership.updated_at
+            ],
+        )?;
+        transaction.commit()?;
+        Ok(Some(membership))
+    }
+
+    fn delete_membership(
+        &self,
+        list_id: &str,
+        user_id: &str,
+    ) -> Result<Option<Membership>, StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        let membership_option = find_membership(&transaction, list_id, user_id)?;
+        if membership_option.is_some() {
+            transaction.execute(
+                "DELETE FROM membership WHERE list_id = ?1 AND user_id = ?2",
+                params![list_id, user_id],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(membership_option)
+    }
+}
+
+impl UserStore for SqliteStore {
+    fn find_user(&self, id: &str) -> Result<Option<User>, StoreError> {
+        let connection = self.reader();
+        Ok(connection
+            .query_row(
+                &format!("SELECT {} FROM user WHERE id = ?1", USER_COLUMNS),
+                params![id],
+                user_from_row,
+            )
+            .optional()?)
+    }
+
+    fn find_user_by_name(&self, name: &str) -> Result<Option<User>, StoreError> {
+        let connection = self.reader();
+        Ok(connection
+            .query_row(
+                &format!(
+                    "SELECT {} FROM user WHERE unicode_lower(name) = ?1",
+                    USER_COLUMNS
+                ),
+                params![name.to_lowercase()],
+                user_from_row,
+            )
+            .optional()?)
+    }
+
+    fn append_user(&self, user: User) -> Result<(), StoreError> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "INSERT INTO user (id, name, password_hash, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    user.id,
+                    user.name,
+                    user.password_hash,
+                    user.created_at,
+                    user.updated_at
+                ],
+            )
+            .map_err(|e| duplicate(e, "user", NAME_INDEX, &user.name.to_lowercase()))?;
+        Ok(())
+    }
+
+    fn find_session(&self, token_hash: &str) -> Result<Option<Session>, StoreError> {
+        let connection = self.reader();
+        Ok(connection
+            .query_row(
+                &format!(
+                    "SELECT {} FROM session WHERE token_hash = ?1",
+                    SESSION_COLUMNS
+                ),
+                params![token_hash],
+                session_from_row,
+            )
+            .optional()?)
+    }
+
+    fn append_session(&self, session: Session) -> Result<(), StoreError> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        // expired sessions are dropped whenever someone signs in
+        transaction.execute(
+            "DELETE FROM session WHERE expires_at <= ?1",
+            params![session.created_at],
+        )?;
+        transaction.execute(
+            "INSERT INTO session (token_hash, user_id, created_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                session.token_hash,
+                session.user_id,
+                session.created_at,
+                session.expires_at
+            ],
+        )?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn delete_session(&self, token_hash: &str) -> Result<bool, StoreError> {
+        let connection = self.connection.lock().unwrap();
+        let deleted = connection.execute(
+            "DELETE FROM session WHERE token_hash = ?1",
+            params![token_hash],
+        )?;
+        Ok(deleted > 0)
+    }
+}
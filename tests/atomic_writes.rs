@@ -0,0 +1,49 @@
+//! The json backend replaces its files atomically and keeps the previous version as `.bak`,
+//! which it recovers from when a file is left unreadable.
+
+#[macro_use]
+mod common;
+
+use std::fs;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+use tempfile::TempDir;
+use todo_list_backend::storage::StorageBackend;
+
+use common::{get, post};
+
+#[actix_web::test]
+async fn recovers_the_lists_from_the_backup_when_their_file_is_torn() {
+    let dir = TempDir::new().unwrap();
+    let app = init_app!(StorageBackend::Json, dir);
+    let token = sign_up!(app, "alice");
+    post_list!(app, token, "groceries");
+    post_list!(app, token, "chores");
+    drop(app);
+
+    // as if the process died while writing the file in place, which write_data never does
+    let filename = dir.path().join("list.json");
+    let contents = fs::read(&filename).unwrap();
+    fs::write(&filename, &contents[..contents.len() / 2]).unwrap();
+    assert!(dir.path().join("list.json.bak").exists());
+    assert!(!dir.path().join("list.json.tmp").exists());
+
+    // the backup holds the lists as they were before the last write
+    let app = init_app!(StorageBackend::Json, dir);
+    let (status, lists) = send!(app, token, get("/api/lists"));
+    assert_eq!(status, StatusCode::OK);
+    let names: Vec<&str> = lists
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|list| list["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["groceries"]);
+
+    // and writing goes on from there
+    let (status, _) = send!(app, token, post("/api/lists", json!({ "name": "chores" })));
+    assert_eq!(status, StatusCode::CREATED);
+    let (_, lists) = send!(app, token, get("/api/lists"));
+    assert_eq!(lists.as_array().unwrap().len(), 2);
+}
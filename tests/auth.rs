@@ -0,0 +1,198 @@
+//! Users register and log in, lists and entries are only served to requests
+//! with the bearer token of a session, on both storage backends.
+
+#[macro_use]
+mod common;
+
+use actix_web::{
+    http::{header, StatusCode},
+    test,
+};
+use chrono::Utc;
+use tempfile::TempDir;
+use todo_list_backend::{
+    models::user::User,
+    prototype_db::WriteError,
+    storage::{self, StoreError},
+};
+
+use common::{credentials, get, post, with_token, BACKENDS};
+
+/// The `WWW-Authenticate` header of the response to the request.
+macro_rules! challenge {
+    ($app:expr, $request:expr) => {{
+        let response = test::call_service(&$app, $request.to_request()).await;
+        response
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .map(|value| value.to_str().unwrap().to_string())
+    }};
+}
+
+#[actix_web::test]
+async fn registers_and_logs_in() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+
+        let request = post(
+            "/api/auth/register",
+            credentials(" alice ", "correct horse"),
+        );
+        let (status, user) = send!(app, request);
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        assert_eq!(user["name"], "alice");
+        assert!(user.get("passwordHash").is_none());
+
+        let request = post("/api/auth/login", credentials("ALICE", "correct horse"));
+        let (status, session) = send!(app, request);
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(session["user"]["_id"], user["_id"]);
+        assert!(session["token"].as_str().unwrap().len() >= 32);
+        assert!(session["expiresAt"].is_string());
+    }
+}
+
+#[actix_web::test]
+async fn rejects_taken_names_and_short_passwords() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        send!(
+            app,
+            post("/api/auth/register", credentials("alice", "correct horse"))
+        );
+
+        let request = post("/api/auth/register", credentials("Alice", "battery staple"));
+        let (status, problem) = send!(app, request);
+        assert_eq!(status, StatusCode::CONFLICT, "{:?}", backend);
+        assert_eq!(problem["code"], "user_already_exists");
+
+        let request = post("/api/auth/register", credentials("bob", "short"));
+        let (status, problem) = send!(app, request);
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{:?}", backend);
+        assert_eq!(problem["details"]["errors"][0]["field"], "password");
+        assert_eq!(problem["details"]["errors"][0]["code"], "too_short");
+    }
+}
+
+#[actix_web::test]
+async fn rejects_wrong_credentials() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        send!(
+            app,
+            post("/api/auth/register", credentials("alice", "correct horse"))
+        );
+
+        for (name, password) in [("alice", "wrong horse"), ("nobody", "correct horse")] {
+            let login = || post("/api/auth/login", credentials(name, password));
+            let (status, problem) = send!(app, login());
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{:?}", backend);
+            assert_eq!(challenge!(app, login()).as_deref(), Some("Bearer"));
+            assert_eq!(problem["code"], "invalid_credentials");
+        }
+    }
+}
+
+#[actix_web::test]
+async fn validates_the_login_like_the_registration() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        send!(
+            app,
+            post("/api/auth/register", credentials("alice", "correct horse"))
+        );
+
+        for (name, password, field) in [("  ", "correct horse", "name"), ("alice", "", "password")]
+        {
+            let request = post("/api/auth/login", credentials(name, password));
+            let (status, problem) = send!(app, request);
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{:?}", backend);
+            assert_eq!(problem["details"]["errors"][0]["field"], field);
+        }
+        let request = post("/api/auth/login", credentials(" alice ", "correct horse"));
+        let (status, _) = send!(app, request);
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn requires_a_session_until_logging_out() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let alice = credentials("alice", "correct horse");
+        send!(app, post("/api/auth/register", alice.clone()));
+        let (_, session) = send!(app, post("/api/auth/login", alice));
+        let token = session["token"].as_str().unwrap();
+
+        let lists = || get("/api/lists");
+        let (status, problem) = send!(app, lists());
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{:?}", backend);
+        assert_eq!(challenge!(app, lists()).as_deref(), Some("Bearer"));
+        assert_eq!(problem["code"], "unauthenticated");
+        assert_eq!(problem["status"], 401);
+
+        let (status, _) = send!(app, with_token(lists(), "not a token"));
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{:?}", backend);
+
+        let (status, _) = send!(app, with_token(lists(), token));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+
+        let logout = test::TestRequest::post().uri("/api/auth/logout");
+        let (status, _) = send!(app, with_token(logout, token));
+        assert_eq!(status, StatusCode::NO_CONTENT, "{:?}", backend);
+
+        let (status, _) = send!(app, with_token(lists(), token));
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn the_store_rejects_names_that_are_taken_ignoring_case() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let store = storage::open(backend, dir.path().to_str().unwrap(), None).unwrap();
+        let user = |id: &str, name: &str| User {
+            id: id.to_string(),
+            name: name.to_string(),
+            password_hash: String::new(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        store.append_user(user("1", "Ärger")).unwrap();
+
+        // two registrations that both passed the check before either was stored
+        match store.append_user(user("2", "ärger")) {
+            Err(StoreError::Write(WriteError::Duplicate {
+                collection, index, ..
+            })) => assert_eq!((collection, index), ("user", "name"), "{:?}", backend),
+            Err(e) => panic!("{:?}: expected a duplicate name, got {}", backend, e),
+            Ok(()) => panic!("{:?}: expected a duplicate name", backend),
+        }
+        assert_eq!(store.find_user_by_name("ÄRGER").unwrap().unwrap().id, "1");
+    }
+}
+
+#[actix_web::test]
+async fn stores_no_token_that_could_sign_in() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let (status, _) = send!(app, token, get("/api/lists"));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+
+        for file in std::fs::read_dir(dir.path()).unwrap() {
+            let path = file.unwrap().path();
+            let contents = std::fs::read(&path).unwrap();
+            let contains_token = contents
+                .windows(token.len())
+                .any(|window| window == token.as_bytes());
+            assert!(!contains_token, "{:?} {}", backend, path.display());
+        }
+    }
+}
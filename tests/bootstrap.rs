@@ -0,0 +1,53 @@
+//! A fresh checkout starts with an empty database on both storage backends,
+//! while a file that is there but unreadable stops the json backend from starting.
+
+#[macro_use]
+mod common;
+
+use std::fs;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+use tempfile::TempDir;
+use todo_list_backend::{
+    prototype_db::LoadError,
+    storage::{self, StorageBackend, StoreError},
+};
+
+use common::{get, BACKENDS};
+
+#[actix_web::test]
+async fn starts_empty_without_a_data_directory() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        fs::remove_dir(dir.path()).unwrap();
+
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let (status, lists) = send!(app, token, get("/api/lists"));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(lists, json!([]), "{:?}", backend);
+        let (status, entries) = send!(app, token, get("/api/entries"));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(entries, json!([]), "{:?}", backend);
+        assert!(dir.path().is_dir(), "{:?}", backend);
+    }
+}
+
+#[test]
+fn reports_a_corrupt_file_instead_of_starting_over() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("list.json"), "{ \"count\": 1, \"data\": [").unwrap();
+
+    let path = dir.path().to_str().unwrap();
+    match storage::open(StorageBackend::Json, path, None) {
+        Err(StoreError::Load(e @ LoadError::Corrupt { .. })) => {
+            assert!(e.to_string().contains("list.json is corrupt"), "{}", e);
+        }
+        Err(e) => panic!("expected a corrupt file, got {}", e),
+        Ok(_) => panic!("expected a corrupt file"),
+    }
+    // nothing was written over it
+    let contents = fs::read_to_string(dir.path().join("list.json")).unwrap();
+    assert_eq!(contents, "{ \"count\": 1, \"data\": [");
+}
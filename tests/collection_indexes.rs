@@ -0,0 +1,202 @@
+//! `Collection` finds items by the keys of its indexes and refuses mutations
+//! that would give two items the same key of a unique index.
+
+use serde::{Deserialize, Serialize};
+use tempfile::TempDir;
+use todo_list_backend::prototype_db::{Collection, JournalOptions, WriteError, ID_INDEX};
+
+const NAME_INDEX: &str = "name";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Item {
+    #[serde(rename = "_id")]
+    id: String,
+    name: String,
+}
+
+fn item(id: &str, name: &str) -> Item {
+    Item {
+        id: id.to_string(),
+        name: name.to_string(),
+    }
+}
+
+fn open(dir: &TempDir, journal_options: Option<JournalOptions>) -> Collection<Item> {
+    Collection::new("item", dir.path().to_str().unwrap(), journal_options)
+        .unwrap()
+        .with_unique_index(ID_INDEX, |item: &Item| item.id.clone())
+        .with_index(NAME_INDEX, |item: &Item| item.name.clone())
+}
+
+fn assert_duplicate<T>(result: Result<T, WriteError>, expected_key: &str) {
+    match result {
+        Err(WriteError::Duplicate {
+            collection,
+            index,
+            key,
+        }) => {
+            assert_eq!(collection, "item");
+            assert_eq!(index, ID_INDEX);
+            assert_eq!(key, expected_key);
+        }
+        Err(e) => panic!("expected a duplicate, got {}", e),
+        Ok(_) => panic!("expected a duplicate"),
+    }
+}
+
+#[test]
+fn rejects_appending_a_duplicate() {
+    for journal_options in [None, Some(JournalOptions::default())] {
+        let dir = TempDir::new().unwrap();
+        let mut collection = open(&dir, journal_options);
+        collection.append(item("a", "first")).unwrap();
+
+        assert_duplicate(collection.append(item("a", "second")), "a");
+        assert_eq!(collection.find(|_| true).len(), 1);
+
+        // nothing of the rejected item was persisted either
+        let collection = open(&dir, journal_options);
+        assert_eq!(collection.find(|_| true), [&item("a", "first")]);
+    }
+}
+
+#[test]
+fn rejects_changing_a_key_to_one_that_is_taken() {
+    let dir = TempDir::new().unwrap();
+    let mut collection = open(&dir, None);
+    collection.append(item("a", "first")).unwrap();
+    collection.append(item("b", "second")).unwrap();
+
+    assert_duplicate(
+        collection.patch_one(|model| model.id == "b", |model| model.id = "a".to_string()),
+        "a",
+    );
+    assert_duplicate(
+        collection.patch_many(|_| true, |model| model.id = "c".to_string()),
+        "c",
+    );
+
+    // keys can be swapped in a single operation and keep their own key when replaced
+    collection
+        .patch_many(
+            |_| true,
+            |model| model.id = if model.id == "a" { "b" } else { "a" }.to_string(),
+        )
+        .unwrap();
+    collection
+        .put_one(|model| model.id == "a", item("a", "renamed"))
+        .unwrap();
+    assert_eq!(
+        collection.find_one_by_key(ID_INDEX, "a"),
+        Some(&item("a", "renamed"))
+    );
+    assert_eq!(
+        collection.find_one_by_key(ID_INDEX, "b"),
+        Some(&item("b", "first"))
+    );
+}
+
+#[test]
+fn finds_items_by_key_after_removals() {
+    let dir = TempDir::new().unwrap();
+    let mut collection = open(&dir, None);
+    for id in ["a", "b", "c", "d"] {
+        collection.append(item(id, id)).unwrap();
+    }
+    collection.delete_one(|model| model.id == "a").unwrap();
+    collection.delete_many(|model| model.id == "c").unwrap();
+
+    assert_eq!(collection.find_one_by_key(ID_INDEX, "a"), None);
+    assert_eq!(
+        collection.find_one_by_key(ID_INDEX, "b"),
+        Some(&item("b", "b"))
+    );
+    assert_eq!(
+        collection.find_one_by_key(ID_INDEX, "d"),
+        Some(&item("d", "d"))
+    );
+
+    // a removed key can be used again
+    collection.append(item("a", "again")).unwrap();
+    assert_eq!(
+        collection.find_one_by_key(ID_INDEX, "a"),
+        Some(&item("a", "again"))
+    );
+}
+
+#[test]
+fn finds_what_a_scan_finds_after_removing_items_in_between() {
+    let dir = TempDir::new().unwrap();
+    let mut collection = open(&dir, None);
+    for number in 0..40 {
+        let name = format!("name {}", number % 7);
+        collection.append(item(&number.to_string(), &name)).unwrap();
+    }
+    collection
+        .delete_many(|model| model.id.len() == 1 || model.id.ends_with('3'))
+        .unwrap();
+    collection.delete_one_by_key(ID_INDEX, "21").unwrap();
+    collection
+        .delete_many_by_keys(NAME_INDEX, ["name 5"])
+        .unwrap();
+    collection.append(item("0", "name 0")).unwrap();
+    collection.delete_one_by_key(ID_INDEX, "10").unwrap();
+
+    for number in 0..40 {
+        let id = number.to_string();
+        assert_eq!(
+            collection.find_one_by_key(ID_INDEX, &id),
+            collection.find_one(|model| model.id == id),
+            "{}",
+            id
+        );
+    }
+    for number in 0..7 {
+        let name = format!("name {}", number);
+        assert_eq!(
+            collection.find_by_key(NAME_INDEX, &name),
+            collection.find(|model| model.name == name),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn finds_all_items_that_share_a_key() {
+    let dir = TempDir::new().unwrap();
+    let mut collection = open(&dir, Some(JournalOptions::default()));
+    for (id, name) in [("a", "x"), ("b", "y"), ("c", "x"), ("d", "x")] {
+        collection.append(item(id, name)).unwrap();
+    }
+    collection
+        .patch_one_by_key(ID_INDEX, "c", |model| model.name = "y".to_string())
+        .unwrap();
+    collection.delete_one_by_key(ID_INDEX, "a").unwrap();
+
+    let ids = |collection: &Collection<Item>, name| -> Vec<String> {
+        collection
+            .find_by_key(NAME_INDEX, name)
+            .into_iter()
+            .map(|item| item.id.clone())
+            .collect()
+    };
+    assert_eq!(ids(&collection, "x"), ["d"]);
+    assert_eq!(ids(&collection, "y"), ["b", "c"]);
+
+    assert_eq!(
+        collection
+            .delete_many_by_keys(NAME_INDEX, ["y", "z"])
+            .unwrap(),
+        2
+    );
+    assert!(ids(&collection, "y").is_empty());
+
+    // the indexes are built again from what was persisted
+    let collection = open(&dir, Some(JournalOptions::default()));
+    assert_eq!(ids(&collection, "x"), ["d"]);
+    assert_eq!(
+        collection.find_one_by_key(ID_INDEX, "d"),
+        Some(&item("d", "x"))
+    );
+}
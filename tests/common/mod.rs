@@ -0,0 +1,168 @@
+//! What the tests share: the app on either storage backend, signed in users, requests
+//! with json bodies and models to store directly. Each test crate uses a different part of it.
+#![allow(dead_code, unused_macros)]
+
+use actix_web::{http::header, test};
+use chrono::Utc;
+use serde_json::{json, Value};
+use todo_list_backend::{
+    models::{entry::Entry, list::List, tag::Tag, user::User},
+    storage::StorageBackend,
+};
+
+pub const BACKENDS: [StorageBackend; 2] = [StorageBackend::Json, StorageBackend::Sqlite];
+
+/// Starts the app on the store of the backend in `dir`, reusing what is already stored there.
+macro_rules! init_app {
+    ($backend:expr, $dir:expr) => {{
+        let store = todo_list_backend::storage::open($backend, $dir.path().to_str().unwrap(), None)
+            .unwrap();
+        actix_web::test::init_service(
+            actix_web::App::new()
+                .app_data(actix_web::web::Data::from(store))
+                .configure(|config| todo_list_backend::configure_api(config, "/api")),
+        )
+        .await
+    }};
+}
+
+/// Sends the request, with the token if one is given, and returns the status
+/// and the json body, `Value::Null` if there is none.
+macro_rules! send {
+    ($app:expr, $token:expr, $request:expr) => {
+        send!($app, $crate::common::with_token($request, &$token))
+    };
+    ($app:expr, $request:expr) => {{
+        let response = actix_web::test::call_service(&$app, $request.to_request()).await;
+        let status = response.status();
+        let body = actix_web::test::read_body(response).await;
+        let body: serde_json::Value = if body.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::from_slice(&body).unwrap()
+        };
+        (status, body)
+    }};
+}
+
+/// Logs in the user registered by `sign_up!` and returns the token of a new session.
+macro_rules! log_in {
+    ($app:expr, $name:expr) => {{
+        let request = $crate::common::post(
+            "/api/auth/login",
+            $crate::common::credentials($name, $crate::common::PASSWORD),
+        );
+        let (status, session) = send!($app, request);
+        assert_eq!(status, actix_web::http::StatusCode::OK);
+        session["token"].as_str().unwrap().to_string()
+    }};
+}
+
+/// Registers the user and returns the token of a new session.
+macro_rules! sign_up {
+    ($app:expr, $name:expr) => {{
+        let request = $crate::common::post(
+            "/api/auth/register",
+            $crate::common::credentials($name, $crate::common::PASSWORD),
+        );
+        let (status, _) = send!($app, request);
+        assert_eq!(status, actix_web::http::StatusCode::CREATED);
+        log_in!($app, $name)
+    }};
+}
+
+/// Creates a list of the user and returns its id.
+macro_rules! post_list {
+    ($app:expr, $token:expr, $name:expr) => {{
+        let request = $crate::common::post("/api/lists", serde_json::json!({ "name": $name }));
+        let (status, list) = send!($app, $token, request);
+        assert_eq!(status, actix_web::http::StatusCode::CREATED);
+        list["_id"].as_str().unwrap().to_string()
+    }};
+}
+
+/// The password of the users `sign_up!` registers.
+pub const PASSWORD: &str = "correct horse";
+
+pub fn credentials(name: &str, password: &str) -> Value {
+    json!({ "name": name, "password": password })
+}
+
+pub fn with_token(request: test::TestRequest, token: &str) -> test::TestRequest {
+    request.insert_header((header::AUTHORIZATION, format!("Bearer {}", token)))
+}
+
+pub fn get(uri: &str) -> test::TestRequest {
+    test::TestRequest::get().uri(uri)
+}
+
+pub fn post(uri: &str, body: Value) -> test::TestRequest {
+    test::TestRequest::post().uri(uri).set_json(body)
+}
+
+pub fn put(uri: &str, body: Value) -> test::TestRequest {
+    test::TestRequest::put().uri(uri).set_json(body)
+}
+
+pub fn patch(uri: &str, body: Value) -> test::TestRequest {
+    test::TestRequest::patch().uri(uri).set_json(body)
+}
+
+pub fn delete(uri: &str) -> test::TestRequest {
+    test::TestRequest::delete().uri(uri)
+}
+
+/// A list without an owner, named like its id.
+pub fn list(id: &str) -> List {
+    let now = Utc::now();
+    List {
+        id: id.to_string(),
+        name: id.to_string(),
+        owner_id: None,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// An entry of the list that is not done, named like its id.
+pub fn entry(id: &str, list_id: &str) -> Entry {
+    let now = Utc::now();
+    Entry {
+        id: id.to_string(),
+        list_id: list_id.to_string(),
+        parent_id: None,
+        name: id.to_string(),
+        done: false,
+        position: 0,
+        due_at: None,
+        priority: None,
+        notes: None,
+        recurrence: None,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// A tag of the owner, `None` for one stored before tags had owners.
+pub fn tag(id: &str, owner_id: Option<&str>, name: &str) -> Tag {
+    let now = Utc::now();
+    Tag {
+        id: id.to_string(),
+        owner_id: owner_id.map(str::to_string),
+        name: name.to_string(),
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// A user named like their id, who cannot log in.
+pub fn user(id: &str) -> User {
+    let now = Utc::now();
+    User {
+        id: id.to_string(),
+        name: id.to_string(),
+        password_hash: String::new(),
+        created_at: now,
+        updated_at: now,
+    }
+}
@@ -0,0 +1,77 @@
+//! Shared collections write their files after unlocking, in whatever order the writers get there.
+
+mod common;
+
+use std::{sync::Arc, thread};
+
+use tempfile::TempDir;
+use todo_list_backend::{
+    prototype_db::{Database, JournalOptions},
+    storage::ListStore,
+};
+
+use common::list;
+
+const THREADS: usize = 8;
+const LISTS_PER_THREAD: usize = 50;
+
+fn open(dir: &TempDir, journal_options: Option<JournalOptions>) -> Database {
+    Database::new(dir.path().to_str().unwrap().to_string(), journal_options).unwrap()
+}
+
+fn count_lists(database: &Database) -> usize {
+    database
+        .query_lists(&Default::default(), Default::default(), Default::default())
+        .unwrap()
+        .total
+}
+
+/// Appends and renames lists from several threads while others read, then reopens the database.
+fn writes_survive_reopening(journal_options: Option<JournalOptions>) {
+    let dir = TempDir::new().unwrap();
+    let database = Arc::new(open(&dir, journal_options));
+
+    let writers = (0..THREADS).map(|thread| {
+        let database = database.clone();
+        thread::spawn(move || {
+            for round in 0..LISTS_PER_THREAD {
+                let id = format!("list-{}-{}", thread, round);
+                database.append_list(list(&id)).unwrap();
+                database
+                    .patch_list(&id, &mut |model| {
+                        model.name = format!("renamed {}", model.id)
+                    })
+                    .unwrap();
+            }
+        })
+    });
+    let readers = (0..THREADS).map(|_| {
+        let database = database.clone();
+        thread::spawn(move || {
+            for _ in 0..LISTS_PER_THREAD {
+                assert!(count_lists(&database) <= THREADS * LISTS_PER_THREAD);
+            }
+        })
+    });
+    let threads: Vec<_> = writers.chain(readers).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    drop(database);
+
+    let database = open(&dir, journal_options);
+    assert_eq!(count_lists(&database), THREADS * LISTS_PER_THREAD);
+    let list = database.find_list("list-3-7").unwrap().unwrap();
+    assert_eq!(list.name, "renamed list-3-7");
+}
+
+#[test]
+fn snapshot_writes_survive_reopening() {
+    writes_survive_reopening(None);
+}
+
+#[test]
+fn journal_writes_survive_reopening() {
+    // compacts several times in between
+    writes_survive_reopening(Some(JournalOptions { compact_after: 37 }));
+}
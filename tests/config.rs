@@ -0,0 +1,93 @@
+//! Settings come from flags, `TODO_*` environment variables and the config file,
+//! in that order of precedence, and invalid ones are reported instead of panicking.
+//! Only one test sets environment variables, the others give what they depend on as flags,
+//! which the environment can't override.
+
+use std::{env, fs};
+
+use tempfile::TempDir;
+use todo_list_backend::{
+    config::{Config, ConfigError},
+    storage::StorageBackend,
+};
+
+fn load(flags: &[&str]) -> Result<Config, ConfigError> {
+    Config::load_from(["todo-list-backend"].iter().chain(flags))
+}
+
+fn write_config_file(dir: &TempDir, contents: &str) -> String {
+    let path = dir.path().join("todo.toml");
+    fs::write(&path, contents).unwrap();
+    path.display().to_string()
+}
+
+#[test]
+fn flags_override_the_environment_which_overrides_the_file() {
+    let dir = TempDir::new().unwrap();
+    let path = write_config_file(
+        &dir,
+        r#"
+            port = 1000
+            api_prefix = "/file"
+            db_dir = "file-db"
+            storage = "sqlite"
+        "#,
+    );
+    env::set_var("TODO_PORT", "2000");
+    env::set_var("TODO_API_PREFIX", "/env");
+    let config = load(&["--config", &path, "--port", "3000"]);
+    env::remove_var("TODO_PORT");
+    env::remove_var("TODO_API_PREFIX");
+
+    let config = config.unwrap();
+    assert_eq!(config.port, 3000);
+    assert_eq!(config.api_prefix, "/env");
+    assert_eq!(config.db_dir, "file-db");
+    assert_eq!(config.storage, StorageBackend::Sqlite);
+    // nothing sets these, so the defaults apply
+    assert_eq!(config.bind_address, "0.0.0.0");
+    assert!(config.journal_options.is_none());
+    assert!(config.cors_allowed_origins.is_none());
+}
+
+#[test]
+fn reports_invalid_values_by_their_setting() {
+    let cases: [(&[&str], &str); 5] = [
+        (&["--bind-address", "localhost"], "bind_address"),
+        (&["--api-prefix", "api/"], "api_prefix"),
+        (&["--db-dir", ""], "db_dir"),
+        (&["--journal-compact-after", "0"], "journal_compact_after"),
+        (
+            &["--cors-allowed-origins", "https://example.com,example.org"],
+            "cors_allowed_origins",
+        ),
+    ];
+    for (flags, expected) in cases {
+        match load(flags) {
+            Err(ConfigError::Invalid { setting, .. }) => assert_eq!(setting, expected),
+            Err(e) => panic!("{:?}: expected an invalid {}, got {}", flags, expected, e),
+            Ok(_) => panic!("{:?}: expected an invalid {}", flags, expected),
+        }
+    }
+}
+
+#[test]
+fn reports_a_config_file_it_cannot_read_or_parse() {
+    let dir = TempDir::new().unwrap();
+    let missing = dir.path().join("missing.toml").display().to_string();
+    assert!(matches!(
+        load(&["--config", &missing]),
+        Err(ConfigError::Read { .. })
+    ));
+
+    for contents in ["port = \"not a number\"", "unknown_setting = true"] {
+        let path = write_config_file(&dir, contents);
+        match load(&["--config", &path]) {
+            Err(e @ ConfigError::Parse { .. }) => {
+                assert!(e.to_string().starts_with("could not parse"), "{}", e)
+            }
+            Err(e) => panic!("{}: expected a parse error, got {}", contents, e),
+            Ok(_) => panic!("{}: expected a parse error", contents),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Entries have an optional due date, priority and notes, and the entries that are overdue,
+//! due today or due this week can be queried across all lists, on both storage backends.
+
+#[macro_use]
+mod common;
+
+use actix_web::http::StatusCode;
+use chrono::{Datelike, Duration, Utc, Weekday};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use common::{get, patch, post, BACKENDS};
+
+fn names(entries: &Value) -> Vec<&str> {
+    entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["name"].as_str().unwrap())
+        .collect()
+}
+
+#[actix_web::test]
+async fn due_dates_priorities_and_notes_can_be_set_and_removed() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "trip");
+
+        let request = post(
+            "/api/entries",
+            json!({
+                "listId": list_id,
+                "name": "book flights",
+                "dueAt": "2030-05-01T12:00:00Z",
+                "priority": "urgent",
+                "notes": "window seat",
+            }),
+        );
+        let (status, entry) = send!(app, token, request);
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        assert_eq!(entry["dueAt"], "2030-05-01T12:00:00Z", "{:?}", backend);
+        assert_eq!(entry["priority"], "urgent", "{:?}", backend);
+        assert_eq!(entry["notes"], "window seat", "{:?}", backend);
+
+        // fields that are not sent are kept, null removes them
+        let uri = format!("/api/entries/{}", entry["_id"].as_str().unwrap());
+        let request = patch(&uri, json!({ "dueAt": null, "priority": "low" }));
+        let (status, entry) = send!(app, token, request);
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert!(entry.get("dueAt").is_none(), "{:?}", backend);
+        assert_eq!(entry["priority"], "low", "{:?}", backend);
+        assert_eq!(entry["notes"], "window seat", "{:?}", backend);
+
+        let request = patch(&uri, json!({ "priority": "critical" }));
+        let (status, problem) = send!(app, token, request);
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{:?}", backend);
+        assert_eq!(problem["code"], "invalid_payload", "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn due_entries_are_found_across_lists() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let bob = sign_up!(app, "bob");
+        let now = Utc::now();
+        let today = now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let entries = [
+            ("yesterday", today - Duration::hours(12), false),
+            ("done yesterday", today - Duration::hours(12), true),
+            ("today", today + Duration::seconds(1), true),
+            ("next year", now + Duration::days(365), false),
+        ];
+        for (index, (name, due_at, done)) in entries.into_iter().enumerate() {
+            // every entry in a list of its own
+            let list_id = post_list!(app, token, format!("list {}", index));
+            let request = post(
+                "/api/entries",
+                json!({ "listId": list_id, "name": name, "dueAt": due_at, "done": done }),
+            );
+            let (status, _) = send!(app, token, request);
+            assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        }
+        let list_id = post_list!(app, token, "undated");
+        let request = post(
+            "/api/entries",
+            json!({ "listId": list_id, "name": "someday" }),
+        );
+        send!(app, token, request);
+
+        let (status, overdue) = send!(app, token, get("/api/entries/overdue"));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(names(&overdue), ["yesterday"], "{:?}", backend);
+        let (_, due_today) = send!(app, token, get("/api/entries/due-today?utcOffset=0"));
+        assert_eq!(names(&due_today), ["today"], "{:?}", backend);
+        let uri = "/api/entries/due-today?utcOffset=0&done=false";
+        let (_, due_today) = send!(app, token, get(uri));
+        assert_eq!(due_today, json!([]), "{:?}", backend);
+        let (_, this_week) = send!(app, token, get("/api/entries/due-this-week"));
+        let expected: &[&str] = if now.weekday() == Weekday::Mon {
+            &["today"]
+        } else {
+            &["yesterday", "done yesterday", "today"]
+        };
+        assert_eq!(names(&this_week), expected, "{:?}", backend);
+
+        // nothing of it is due for anyone else
+        let (_, overdue) = send!(app, bob, get("/api/entries/overdue"));
+        assert_eq!(overdue, json!([]), "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn the_utc_offset_has_to_be_one_in_use() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+
+        for uri in [
+            "/api/entries/due-today?utcOffset=841",
+            "/api/entries/due-this-week?utcOffset=-721",
+        ] {
+            let (status, problem) = send!(app, token, get(uri));
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{:?}", backend);
+            let error = &problem["details"]["errors"][0];
+            assert_eq!(error["field"], "utcOffset", "{:?}", backend);
+            assert_eq!(error["code"], "out_of_range", "{:?}", backend);
+        }
+        let (status, _) = send!(app, token, get("/api/entries/due-today?utcOffset=840"));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+    }
+}
@@ -0,0 +1,95 @@
+//! A collection replays the operations its journal holds beyond the snapshot when it is
+//! opened, and refuses a journal whose operations don't fit the data.
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tempfile::TempDir;
+use todo_list_backend::prototype_db::{Collection, JournalOptions, LoadError};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Item {
+    #[serde(rename = "_id")]
+    id: String,
+}
+
+/// Writes a snapshot with the items `a` and `b` and a journal with the records, one per line.
+fn write_files(dir: &TempDir, records: &[serde_json::Value]) {
+    let snapshot = json!({
+        "count": 2,
+        "sequence": 3,
+        "data": [{ "_id": "a" }, { "_id": "b" }],
+    });
+    fs::write(dir.path().join("item.json"), snapshot.to_string()).unwrap();
+    let journal: String = records
+        .iter()
+        .map(|record| format!("{}\n", record))
+        .collect();
+    fs::write(dir.path().join("item.journal"), journal).unwrap();
+}
+
+fn open(dir: &TempDir) -> Result<Collection<Item>, LoadError> {
+    Collection::new(
+        "item",
+        dir.path().to_str().unwrap(),
+        Some(JournalOptions::default()),
+    )
+}
+
+#[test]
+fn replays_the_operations_after_the_snapshot() {
+    let dir = TempDir::new().unwrap();
+    write_files(
+        &dir,
+        &[
+            // already part of the snapshot
+            json!({ "sequence": 3, "operation": { "op": "remove", "index": 7 } }),
+            json!({ "sequence": 4, "operation": { "op": "append", "data": { "_id": "c" } } }),
+            json!({ "sequence": 5, "operation": { "op": "removeMany", "indices": [0, 2] } }),
+        ],
+    );
+
+    let collection = open(&dir).unwrap();
+    let ids: Vec<&str> = collection
+        .find(|_| true)
+        .into_iter()
+        .map(|item| item.id.as_str())
+        .collect();
+    assert_eq!(ids, ["b"]);
+}
+
+#[test]
+fn refuses_operations_on_positions_the_data_does_not_have() {
+    let operations = [
+        json!({ "op": "replace", "index": 2, "data": { "_id": "c" } }),
+        json!({ "op": "replaceMany", "replacements": [[0, { "_id": "c" }], [5, { "_id": "d" }]] }),
+        json!({ "op": "remove", "index": 2 }),
+        json!({ "op": "removeMany", "indices": [0, 2] }),
+        // removing from the back only keeps the other positions valid if they ascend
+        json!({ "op": "removeMany", "indices": [1, 0] }),
+    ];
+    for operation in operations {
+        let dir = TempDir::new().unwrap();
+        write_files(
+            &dir,
+            &[
+                json!({ "sequence": 4, "operation": { "op": "append", "data": { "_id": "c" } } }),
+                json!({ "sequence": 5, "operation": { "op": "remove", "index": 2 } }),
+                json!({ "sequence": 6, "operation": operation }),
+            ],
+        );
+
+        match open(&dir) {
+            Err(LoadError::InvalidOperation { filename, sequence }) => {
+                assert!(filename.ends_with("item.journal"), "{}", filename);
+                assert_eq!(sequence, 6, "{}", operation);
+            }
+            Err(e) => panic!("{}: expected an invalid operation, got {}", operation, e),
+            Ok(_) => panic!("{}: expected an invalid operation", operation),
+        }
+        // the snapshot is left as it was
+        let snapshot = fs::read_to_string(dir.path().join("item.json")).unwrap();
+        assert!(snapshot.contains("\"sequence\":3"), "{}", snapshot);
+    }
+}
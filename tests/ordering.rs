@@ -0,0 +1,136 @@
+//! Entries keep the order they are given within their list, on both storage backends.
+//! A list is reordered as a whole, a single entry is moved by its index.
+
+#[macro_use]
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use common::{get, patch, post, put, BACKENDS};
+
+fn names(entries: &Value) -> Vec<&str> {
+    entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["name"].as_str().unwrap())
+        .collect()
+}
+
+fn names_of_list(list: &Value) -> Vec<&str> {
+    names(&list["children"])
+}
+
+/// Adds entries of the names to the list and returns their ids in the same order.
+macro_rules! post_entries {
+    ($app:expr, $token:expr, $list_id:expr, $names:expr) => {{
+        let mut ids = Vec::new();
+        for name in $names {
+            let request = post("/api/entries", json!({ "listId": $list_id, "name": name }));
+            let (status, entry) = send!($app, $token, request);
+            assert_eq!(status, StatusCode::CREATED);
+            ids.push(entry["_id"].as_str().unwrap().to_string());
+        }
+        ids
+    }};
+}
+
+#[actix_web::test]
+async fn entries_are_listed_in_their_order() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "groceries");
+        let ids = post_entries!(app, token, list_id, ["milk", "eggs", "bread"]);
+        let list_uri = format!("/api/lists/{}/entries", list_id);
+        let (_, list) = send!(app, token, get(&list_uri));
+        assert_eq!(
+            names_of_list(&list),
+            ["milk", "eggs", "bread"],
+            "{:?}",
+            backend
+        );
+
+        let request = post(
+            &format!("/api/lists/{}/entries/reorder", list_id),
+            json!({ "ids": [ids[2], ids[0], ids[1]] }),
+        );
+        let (status, entries) = send!(app, token, request);
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(names(&entries), ["bread", "milk", "eggs"], "{:?}", backend);
+        let (_, list) = send!(app, token, get(&list_uri));
+        assert_eq!(
+            names_of_list(&list),
+            ["bread", "milk", "eggs"],
+            "{:?}",
+            backend
+        );
+
+        // moving one entry leaves the others in their order
+        let uri = format!("/api/entries/{}", ids[1]);
+        let (status, _) = send!(app, token, patch(&uri, json!({ "index": 0 })));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        let (_, list) = send!(app, token, get(&list_uri));
+        assert_eq!(
+            names_of_list(&list),
+            ["eggs", "bread", "milk"],
+            "{:?}",
+            backend
+        );
+
+        // replacing an entry keeps it where it is
+        let request = put(
+            &format!("/api/entries/{}", ids[2]),
+            json!({ "listId": list_id, "name": "rye bread", "done": false }),
+        );
+        let (status, _) = send!(app, token, request);
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        let (_, list) = send!(app, token, get(&list_uri));
+        assert_eq!(
+            names_of_list(&list),
+            ["eggs", "rye bread", "milk"],
+            "{:?}",
+            backend
+        );
+        let uri = format!("/api/entries?listId={}&sort=position", list_id);
+        let (_, entries) = send!(app, token, get(&uri));
+        assert_eq!(
+            names(&entries),
+            ["eggs", "rye bread", "milk"],
+            "{:?}",
+            backend
+        );
+    }
+}
+
+#[actix_web::test]
+async fn reordering_requires_every_entry_of_the_list_once() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "groceries");
+        let ids = post_entries!(app, token, list_id, ["milk", "eggs"]);
+        let other_list_id = post_list!(app, token, "chores");
+        let other_ids = post_entries!(app, token, other_list_id, ["dishes"]);
+        let uri = format!("/api/lists/{}/entries/reorder", list_id);
+
+        for (ids, code) in [
+            (vec![&ids[1]], "incomplete"),
+            (vec![&ids[1], &ids[0], &other_ids[0]], "incomplete"),
+            (vec![&ids[1], &other_ids[0]], "incomplete"),
+            (vec![&ids[1], &ids[0], &ids[1]], "duplicate"),
+        ] {
+            let (status, problem) = send!(app, token, post(&uri, json!({ "ids": ids })));
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{:?}", backend);
+            let error = &problem["details"]["errors"][0];
+            assert_eq!(error["field"], "ids", "{:?}", backend);
+            assert_eq!(error["code"], code, "{:?}", backend);
+        }
+        let (_, list) = send!(app, token, get(&format!("/api/lists/{}/entries", list_id)));
+        assert_eq!(names_of_list(&list), ["milk", "eggs"], "{:?}", backend);
+    }
+}
@@ -0,0 +1,182 @@
+//! Users only get to see and change their own lists and the entries in them,
+//! lists of other users respond like missing ones. Lists from before there were users
+//! belong to whoever claims them.
+
+#[macro_use]
+mod common;
+
+use actix_web::http::StatusCode;
+use chrono::Utc;
+use serde_json::json;
+use tempfile::TempDir;
+use todo_list_backend::{
+    models::{list::List, tag::Tag},
+    storage,
+};
+
+use common::{delete, get, patch, post, put, BACKENDS};
+
+#[actix_web::test]
+async fn lists_and_entries_of_other_users_are_hidden() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let alice = sign_up!(app, "alice");
+        let bob = sign_up!(app, "bob");
+
+        let request = post("/api/lists", json!({ "name": "groceries" }));
+        let (status, list) = send!(app, alice, request);
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        let list_id = list["_id"].as_str().unwrap();
+        let request = post(
+            "/api/entries",
+            json!({ "listId": list_id, "name": "milk", "dueAt": "2000-01-01T00:00:00Z" }),
+        );
+        let (status, entry) = send!(app, alice, request);
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        let entry_id = entry["_id"].as_str().unwrap();
+
+        // alice sees her own data
+        let (_, lists) = send!(app, alice, get("/api/lists"));
+        assert_eq!(lists.as_array().unwrap().len(), 1, "{:?}", backend);
+        let (status, _) = send!(app, alice, get(&format!("/api/lists/{}", list_id)));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+
+        // bob sees none of it
+        let (_, lists) = send!(app, bob, get("/api/lists"));
+        assert_eq!(lists, json!([]), "{:?}", backend);
+        for uri in [
+            format!("/api/lists/{}", list_id),
+            format!("/api/lists/{}/entries", list_id),
+            format!("/api/lists/{}/tree", list_id),
+            format!("/api/entries/{}", entry_id),
+            format!("/api/entries/{}/tree", entry_id),
+        ] {
+            let (status, problem) = send!(app, bob, get(&uri));
+            assert_eq!(status, StatusCode::NOT_FOUND, "{:?} {}", backend, uri);
+            assert!(problem["code"].as_str().unwrap().ends_with("_not_found"));
+        }
+        for uri in [
+            "/api/entries".to_string(),
+            format!("/api/entries?listId={}", list_id),
+            "/api/entries/overdue".to_string(),
+        ] {
+            let (status, entries) = send!(app, bob, get(&uri));
+            assert_eq!(status, StatusCode::OK, "{:?} {}", backend, uri);
+            assert_eq!(entries, json!([]), "{:?} {}", backend, uri);
+        }
+        let (_, groups) = send!(app, bob, get("/api/search?q=milk"));
+        assert_eq!(groups, json!([]), "{:?}", backend);
+        let (_, groups) = send!(app, alice, get("/api/search?q=milk"));
+        assert_eq!(groups.as_array().unwrap().len(), 1, "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn lists_and_entries_of_other_users_cannot_be_changed() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let alice = sign_up!(app, "alice");
+        let bob = sign_up!(app, "bob");
+
+        let request = post("/api/lists", json!({ "name": "groceries" }));
+        let (_, list) = send!(app, alice, request);
+        let list_id = list["_id"].as_str().unwrap();
+        let request = post("/api/entries", json!({ "listId": list_id, "name": "milk" }));
+        let (_, entry) = send!(app, alice, request);
+        let entry_id = entry["_id"].as_str().unwrap();
+        let request = post("/api/lists", json!({ "name": "chores" }));
+        let (_, own_list) = send!(app, bob, request);
+        let own_list_id = own_list["_id"].as_str().unwrap();
+
+        let list_uri = format!("/api/lists/{}", list_id);
+        let entry_uri = format!("/api/entries/{}", entry_id);
+        let requests = [
+            patch(&list_uri, json!({ "name": "mine" })),
+            put(&list_uri, json!({ "name": "mine" })),
+            delete(&list_uri),
+            post(
+                "/api/entries",
+                json!({ "listId": list_id, "name": "bread" }),
+            ),
+            patch(&entry_uri, json!({ "done": true })),
+            put(
+                &entry_uri,
+                json!({ "listId": own_list_id, "name": "stolen", "done": false }),
+            ),
+            post(
+                "/api/entries",
+                json!({ "listId": own_list_id, "parentId": entry_id, "name": "child" }),
+            ),
+        ];
+        for request in requests {
+            let (status, _) = send!(app, bob, request);
+            assert_eq!(status, StatusCode::NOT_FOUND, "{:?}", backend);
+        }
+        let (status, _) = send!(app, bob, delete(&entry_uri));
+        assert_eq!(status, StatusCode::NO_CONTENT, "{:?}", backend);
+
+        // everything is as alice left it
+        let (_, list) = send!(app, alice, get(&format!("{}/entries", list_uri)));
+        assert_eq!(list["parent"]["name"], "groceries", "{:?}", backend);
+        assert_eq!(list["children"][0]["name"], "milk", "{:?}", backend);
+        assert_eq!(list["children"][0]["done"], false, "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn a_user_can_claim_the_lists_and_tags_from_before_there_were_users() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let alice = sign_up!(app, "alice");
+        let (status, _) = send!(app, alice, post("/api/tags", json!({ "name": "home" })));
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        drop(app);
+
+        let store = storage::open(backend, dir.path().to_str().unwrap(), None).unwrap();
+        let now = Utc::now();
+        store
+            .append_list(List {
+                id: "legacy".to_string(),
+                name: "groceries".to_string(),
+                owner_id: None,
+                created_at: now,
+                updated_at: now,
+            })
+            .unwrap();
+        for name in ["Home", "work"] {
+            store
+                .append_tag(Tag {
+                    id: name.to_string(),
+                    owner_id: None,
+                    name: name.to_string(),
+                    created_at: now,
+                    updated_at: now,
+                })
+                .unwrap();
+        }
+        let claimed = storage::claim_ownerless(&*store, "nobody").unwrap();
+        assert_eq!(claimed, None, "{:?}", backend);
+        let claimed = storage::claim_ownerless(&*store, "Alice").unwrap();
+        // alice already has a tag named home, that one stays without an owner
+        assert_eq!(claimed, Some((1, 1)), "{:?}", backend);
+        drop(store);
+
+        let app = init_app!(backend, dir);
+        let alice = log_in!(app, "alice");
+        let (_, lists) = send!(app, alice, get("/api/lists"));
+        assert_eq!(lists[0]["_id"], "legacy", "{:?}", backend);
+        let (status, _) = send!(app, alice, get("/api/lists/legacy/entries"));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        let (_, tags) = send!(app, alice, get("/api/tags?sort=name"));
+        let names: Vec<&str> = tags
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tag| tag["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["home", "work"], "{:?}", backend);
+    }
+}
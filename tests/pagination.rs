@@ -0,0 +1,52 @@
+//! Paginated responses on both storage backends.
+
+#[macro_use]
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+use tempfile::TempDir;
+
+use common::{get, BACKENDS};
+
+#[actix_web::test]
+async fn an_offset_past_the_end_responds_with_nothing() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        post_list!(app, token, "groceries");
+
+        for uri in [
+            format!("/api/lists?limit=10&offset={}", usize::MAX),
+            format!("/api/lists?offset={}", usize::MAX),
+            format!("/api/entries?limit=10&offset={}", u64::MAX),
+        ] {
+            let (status, items) = send!(app, token, get(&uri));
+            assert_eq!(status, StatusCode::OK, "{:?} {}", backend, uri);
+            assert_eq!(items, json!([]), "{:?} {}", backend, uri);
+        }
+    }
+}
+
+#[actix_web::test]
+async fn sorting_by_name_ignores_the_case_of_all_letters() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        for name in ["Ärger", "zebra", "äpfel", "Apple"] {
+            post_list!(app, token, name);
+        }
+
+        let (status, lists) = send!(app, token, get("/api/lists?sort=name"));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        let names: Vec<&str> = lists
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|list| list["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["Apple", "zebra", "äpfel", "Ärger"], "{:?}", backend);
+    }
+}
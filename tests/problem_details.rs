@@ -0,0 +1,115 @@
+//! Errors are answered with RFC 7807 problem details carrying a machine-readable code,
+//! on both storage backends.
+
+#[macro_use]
+mod common;
+
+use actix_web::{
+    http::{header, StatusCode},
+    test,
+};
+use serde_json::json;
+use tempfile::TempDir;
+
+use common::{credentials, get, patch, post, with_token, BACKENDS, PASSWORD};
+
+#[actix_web::test]
+async fn missing_resources_are_problems_with_a_code_per_resource() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+
+        let request = with_token(get("/api/lists/nothing"), &token).to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{:?}", backend);
+        let content_type = response.headers().get(header::CONTENT_TYPE).unwrap();
+        assert_eq!(content_type, "application/problem+json", "{:?}", backend);
+        let problem: serde_json::Value = test::read_body_json(response).await;
+        let expected = json!({
+            "type": "about:blank",
+            "title": "Not Found",
+            "status": 404,
+            "code": "list_not_found",
+            "detail": "list not found",
+            "details": { "resource": "list", "id": "nothing" },
+        });
+        assert_eq!(problem, expected, "{:?}", backend);
+
+        for (request, code) in [
+            (get("/api/entries/nothing"), "entry_not_found"),
+            (get("/api/tags/nothing"), "tag_not_found"),
+            (
+                patch("/api/entries/nothing", json!({ "done": true })),
+                "entry_not_found",
+            ),
+        ] {
+            let (status, problem) = send!(app, token, request);
+            assert_eq!(status, StatusCode::NOT_FOUND, "{:?}", backend);
+            assert_eq!(problem["code"], code, "{:?}", backend);
+            assert_eq!(problem["status"], 404, "{:?}", backend);
+        }
+    }
+}
+
+#[actix_web::test]
+async fn conflicts_name_the_field_and_the_value_that_is_taken() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        sign_up!(app, "alice");
+
+        let request = post("/api/auth/register", credentials("Alice", PASSWORD));
+        let (status, problem) = send!(app, request);
+        assert_eq!(status, StatusCode::CONFLICT, "{:?}", backend);
+        assert_eq!(problem["code"], "user_already_exists", "{:?}", backend);
+        assert_eq!(problem["status"], 409, "{:?}", backend);
+        let details = json!({ "resource": "user", "field": "name", "value": "Alice" });
+        assert_eq!(problem["details"], details, "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn requests_without_a_valid_session_are_unauthenticated() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+
+        for request in [
+            get("/api/lists"),
+            with_token(get("/api/lists"), "not a token"),
+        ] {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{:?}", backend);
+            let challenge = response.headers().get(header::WWW_AUTHENTICATE).unwrap();
+            assert_eq!(challenge, "Bearer", "{:?}", backend);
+            let problem: serde_json::Value = test::read_body_json(response).await;
+            assert_eq!(problem["code"], "unauthenticated", "{:?}", backend);
+            assert!(problem.get("details").is_none(), "{:?}", backend);
+        }
+
+        let request = post("/api/auth/login", credentials("nobody", PASSWORD));
+        let (status, problem) = send!(app, request);
+        assert_eq!(status, StatusCode::UNAUTHORIZED, "{:?}", backend);
+        assert_eq!(problem["code"], "invalid_credentials", "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn query_strings_of_the_wrong_shape_are_invalid_queries() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+
+        for uri in [
+            "/api/lists?limit=many",
+            "/api/entries?done=maybe",
+            "/api/entries?sort=color",
+        ] {
+            let (status, problem) = send!(app, token, get(uri));
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{:?} {}", backend, uri);
+            assert_eq!(problem["code"], "invalid_query", "{:?} {}", backend, uri);
+        }
+    }
+}
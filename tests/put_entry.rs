@@ -0,0 +1,318 @@
+//! PUT /api/entries/{id} replaces an entry or creates it with the given id,
+//! on both storage backends.
+
+#[macro_use]
+mod common;
+
+use actix_web::{http::StatusCode, test};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use common::{get, BACKENDS};
+
+fn put(id: &str, body: Value) -> test::TestRequest {
+    common::put(&format!("/api/entries/{}", id), body)
+}
+
+#[actix_web::test]
+async fn creates_the_entry_with_the_given_id() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "groceries");
+
+        let (status, entry) = send!(
+            app,
+            token,
+            put(
+                "milk",
+                json!({ "listId": list_id, "name": "milk", "done": false })
+            )
+        );
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        assert_eq!(entry["_id"], "milk");
+        assert_eq!(entry["listId"], list_id.as_str());
+        assert_eq!(entry["name"], "milk");
+
+        let (status, found) = send!(app, token, get("/api/entries/milk"));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(found, entry);
+    }
+}
+
+#[actix_web::test]
+async fn replaces_an_existing_entry_without_duplicating_it() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "groceries");
+        let (_, first) = send!(
+            app,
+            token,
+            put(
+                "milk",
+                json!({ "listId": list_id, "name": "milk", "done": false })
+            )
+        );
+        send!(
+            app,
+            token,
+            put(
+                "eggs",
+                json!({ "listId": list_id, "name": "eggs", "done": false })
+            )
+        );
+
+        let body =
+            json!({ "listId": list_id, "name": "oat milk", "done": true, "priority": "high" });
+        let (status, replaced) = send!(app, token, put("milk", body.clone()));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(replaced["name"], "oat milk");
+        assert_eq!(replaced["done"], true);
+        assert_eq!(replaced["priority"], "high");
+        assert_eq!(replaced["createdAt"], first["createdAt"]);
+        assert_eq!(replaced["position"], first["position"]);
+
+        // putting the same entry again changes nothing but the time it was updated
+        let (status, again) = send!(app, token, put("milk", body));
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(again["name"], replaced["name"]);
+        assert_eq!(again["position"], replaced["position"]);
+
+        let (_, list) = send!(app, token, get(&format!("/api/lists/{}/entries", list_id)));
+        let names: Vec<&Value> = list["children"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| &entry["name"])
+            .collect();
+        assert_eq!(names, [&json!("oat milk"), &json!("eggs")], "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn replaced_entries_are_not_duplicated_on_disk() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let list_id = {
+            let app = init_app!(backend, dir);
+            let token = sign_up!(app, "alice");
+            let list_id = post_list!(app, token, "groceries");
+            for name in ["milk", "oat milk"] {
+                send!(
+                    app,
+                    token,
+                    put(
+                        "milk",
+                        json!({ "listId": list_id, "name": name, "done": false })
+                    )
+                );
+            }
+            list_id
+        };
+
+        // the user is already there from the first start
+        let app = init_app!(backend, dir);
+        let token = log_in!(app, "alice");
+        let (_, list) = send!(app, token, get(&format!("/api/lists/{}/entries", list_id)));
+        let entries = list["children"].as_array().unwrap();
+        assert_eq!(entries.len(), 1, "{:?}", backend);
+        assert_eq!(entries[0]["name"], "oat milk");
+    }
+}
+
+#[actix_web::test]
+async fn moves_a_replaced_entry_to_the_end_of_another_list() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let groceries = post_list!(app, token, "groceries");
+        let chores = post_list!(app, token, "chores");
+        send!(
+            app,
+            token,
+            put(
+                "milk",
+                json!({ "listId": groceries, "name": "milk", "done": false })
+            )
+        );
+        send!(
+            app,
+            token,
+            put(
+                "dishes",
+                json!({ "listId": chores, "name": "dishes", "done": false })
+            )
+        );
+
+        let (status, moved) = send!(
+            app,
+            token,
+            put(
+                "milk",
+                json!({ "listId": chores, "name": "milk", "done": false })
+            )
+        );
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(moved["listId"], chores.as_str());
+
+        let (_, list) = send!(app, token, get(&format!("/api/lists/{}/entries", chores)));
+        let ids: Vec<&Value> = list["children"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| &entry["_id"])
+            .collect();
+        assert_eq!(ids, [&json!("dishes"), &json!("milk")], "{:?}", backend);
+        let (_, list) = send!(
+            app,
+            token,
+            get(&format!("/api/lists/{}/entries", groceries))
+        );
+        assert_eq!(list["children"], json!([]));
+    }
+}
+
+#[actix_web::test]
+async fn rejects_a_list_that_does_not_exist() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "groceries");
+        send!(
+            app,
+            token,
+            put(
+                "milk",
+                json!({ "listId": list_id, "name": "milk", "done": false })
+            )
+        );
+
+        // an entry id is not a list id
+        let (status, problem) = send!(
+            app,
+            token,
+            put(
+                "eggs",
+                json!({ "listId": "milk", "name": "eggs", "done": false })
+            )
+        );
+        assert_eq!(status, StatusCode::NOT_FOUND, "{:?}", backend);
+        assert_eq!(problem["details"]["resource"], "list");
+
+        let (status, _) = send!(app, token, get("/api/entries/eggs"));
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
+
+#[actix_web::test]
+async fn validates_the_body() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "groceries");
+        let chores = post_list!(app, token, "chores");
+        send!(
+            app,
+            token,
+            put(
+                "dishes",
+                json!({ "listId": chores, "name": "dishes", "done": false })
+            )
+        );
+
+        // the fields are camelCase like everywhere else
+        let (status, _) = send!(
+            app,
+            token,
+            put(
+                "milk",
+                json!({ "list_id": list_id, "name": "milk", "done": false })
+            )
+        );
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{:?}", backend);
+
+        let (status, problem) = send!(
+            app,
+            token,
+            put(
+                "milk",
+                json!({ "listId": list_id, "name": " ", "done": false })
+            )
+        );
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(problem["details"]["errors"][0]["field"], "name");
+
+        let (status, problem) = send!(
+            app,
+            token,
+            put(
+                "milk",
+                json!({ "listId": list_id, "parentId": "dishes", "name": "milk", "done": false })
+            )
+        );
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(problem["details"]["errors"][0]["field"], "parentId");
+    }
+}
+
+#[actix_web::test]
+async fn keeps_the_sub_entries_of_a_replaced_entry() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "groceries");
+        send!(
+            app,
+            token,
+            put(
+                "baking",
+                json!({ "listId": list_id, "name": "baking", "done": false })
+            )
+        );
+        let (status, _) = send!(
+            app,
+            token,
+            put(
+                "flour",
+                json!({ "listId": list_id, "parentId": "baking", "name": "flour", "done": false })
+            )
+        );
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+
+        let (status, _) = send!(
+            app,
+            token,
+            put(
+                "baking",
+                json!({ "listId": list_id, "name": "cake", "done": false })
+            )
+        );
+        assert_eq!(status, StatusCode::OK);
+
+        let (_, tree) = send!(app, token, get("/api/entries/baking/tree"));
+        assert_eq!(tree["parent"]["name"], "cake");
+        assert_eq!(
+            tree["children"][0]["parent"]["_id"], "flour",
+            "{:?}",
+            backend
+        );
+
+        // an entry can't become a sub-entry of its own sub-entry
+        let (status, _) = send!(
+            app,
+            token,
+            put(
+                "baking",
+                json!({ "listId": list_id, "parentId": "flour", "name": "cake", "done": false })
+            )
+        );
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
@@ -0,0 +1,62 @@
+//! Completing a recurring entry adds its next occurrence, on both storage backends.
+
+#[macro_use]
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use common::{get, patch, post, put, BACKENDS};
+
+#[actix_web::test]
+async fn completing_adds_the_next_occurrence_once() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "chores");
+        let request = post(
+            "/api/entries",
+            json!({
+                "listId": list_id,
+                "name": "water the plants",
+                "dueAt": "2024-01-01T08:00:00Z",
+                "recurrence": "FREQ=WEEKLY;COUNT=3",
+            }),
+        );
+        let (status, entry) = send!(app, token, request);
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        let entry_uri = format!("/api/entries/{}", entry["_id"].as_str().unwrap());
+        let (_, tag) = send!(app, token, post("/api/tags", json!({ "name": "garden" })));
+        let tag_uri = format!("{}/tags/{}", entry_uri, tag["_id"].as_str().unwrap());
+        let (status, _) = send!(app, token, put(&tag_uri, json!({})));
+        assert_eq!(status, StatusCode::NO_CONTENT, "{:?}", backend);
+
+        // the completed entry keeps its rule, reopening and completing it again
+        // adds no second occurrence
+        for done in [true, false, true] {
+            let (status, entry) = send!(app, token, patch(&entry_uri, json!({ "done": done })));
+            assert_eq!(status, StatusCode::OK, "{:?}", backend);
+            assert_eq!(entry["recurrence"]["count"], 3, "{:?}", backend);
+        }
+
+        let uri = format!("/api/entries?listId={}&sort=position", list_id);
+        let (_, entries) = send!(app, token, get(&uri));
+        let entries = entries.as_array().unwrap();
+        assert_eq!(entries.len(), 2, "{:?}", backend);
+        let next = &entries[1];
+        assert_eq!(next["done"], false, "{:?}", backend);
+        assert_eq!(next["dueAt"], "2024-01-08T08:00:00Z", "{:?}", backend);
+        assert_eq!(next["recurrence"]["count"], 2, "{:?}", backend);
+        let uri = format!("/api/entries/{}/tags", next["_id"].as_str().unwrap());
+        let (_, tags) = send!(app, token, get(&uri));
+        let names: Vec<&Value> = tags
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| &t["name"])
+            .collect();
+        assert_eq!(names, [&json!("garden")], "{:?}", backend);
+    }
+}
@@ -0,0 +1,146 @@
+//! GET /api/search finds lists and entries by name, ignoring case, on both storage backends.
+//! The results are grouped by list and ranked by how well the names match.
+
+#[macro_use]
+mod common;
+
+use actix_web::{http::StatusCode, test};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use common::{get, post, with_token, BACKENDS};
+
+/// The name of each list of the results, followed by the names of its entries.
+fn names(groups: &Value) -> Vec<(&str, Vec<&str>)> {
+    groups
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|group| {
+            let entries = group["children"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|entry| entry["name"].as_str().unwrap())
+                .collect();
+            (group["parent"]["name"].as_str().unwrap(), entries)
+        })
+        .collect()
+}
+
+#[actix_web::test]
+async fn groups_the_matches_by_list_and_ranks_them() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let lists = [
+            ("Chores", vec!["Buy MILK powder", "dishes"]),
+            ("Milk run", vec![]),
+            ("Groceries", vec!["Oat milk", "eggs", "milk"]),
+            ("Misc", vec!["bread"]),
+        ];
+        for (list_name, entry_names) in lists {
+            let list_id = post_list!(app, token, list_name);
+            for name in entry_names {
+                let request = post("/api/entries", json!({ "listId": list_id, "name": name }));
+                let (status, _) = send!(app, token, request);
+                assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+            }
+        }
+
+        let (status, groups) = send!(app, token, get("/api/search?q=%20Milk"));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        // an exact name first, then names starting with the term, then the rest
+        let expected = vec![
+            ("Groceries", vec!["milk", "Oat milk"]),
+            ("Milk run", vec![]),
+            ("Chores", vec!["Buy MILK powder"]),
+        ];
+        assert_eq!(names(&groups), expected, "{:?}", backend);
+
+        let (_, groups) = send!(app, token, get("/api/search?q=nothing"));
+        assert_eq!(groups, json!([]), "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn pages_the_groups() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        for name in ["milk", "milk run", "oat milk"] {
+            post_list!(app, token, name);
+        }
+
+        let request = with_token(get("/api/search?q=milk&limit=1&offset=1"), &token);
+        let response = test::call_service(&app, request.to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK, "{:?}", backend);
+        let headers = response.headers();
+        assert_eq!(headers.get("X-Total-Count").unwrap(), "3", "{:?}", backend);
+        assert!(
+            headers
+                .get("Link")
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .contains("</api/search?q=milk&limit=1&offset=2>; rel=\"next\""),
+            "{:?}",
+            backend
+        );
+        let groups: Value = test::read_body_json(response).await;
+        assert_eq!(names(&groups), vec![("milk run", vec![])], "{:?}", backend);
+
+        let (status, problem) = send!(app, token, get("/api/search?q=milk&limit=0"));
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{:?}", backend);
+        assert_eq!(
+            problem["details"]["errors"][0]["field"], "limit",
+            "{:?}",
+            backend
+        );
+    }
+}
+
+#[actix_web::test]
+async fn ignores_the_case_of_all_letters() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "Ärger");
+        let request = post(
+            "/api/entries",
+            json!({ "listId": list_id, "name": "ÄPFEL" }),
+        );
+        send!(app, token, request);
+
+        let (_, groups) = send!(app, token, get("/api/search?q=%C3%A4"));
+        assert_eq!(
+            names(&groups),
+            vec![("Ärger", vec!["ÄPFEL"])],
+            "{:?}",
+            backend
+        );
+    }
+}
+
+#[actix_web::test]
+async fn requires_a_term() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+
+        let (status, problem) = send!(app, token, get("/api/search?q=%20%20"));
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{:?}", backend);
+        assert_eq!(
+            problem["details"]["errors"][0]["field"], "q",
+            "{:?}",
+            backend
+        );
+        let (status, problem) = send!(app, token, get("/api/search"));
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{:?}", backend);
+        assert_eq!(problem["code"], "invalid_query", "{:?}", backend);
+    }
+}
@@ -0,0 +1,178 @@
+//! Lists can be shared with other users, what they may do depends on the role
+//! they were given.
+
+#[macro_use]
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+use tempfile::TempDir;
+
+use common::{delete, get, patch, post, BACKENDS};
+
+#[actix_web::test]
+async fn roles_decide_what_members_may_do() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let alice = sign_up!(app, "alice");
+        let bob = sign_up!(app, "bob");
+
+        let (_, list) = send!(
+            app,
+            alice,
+            post("/api/lists", json!({ "name": "groceries" }))
+        );
+        let list_uri = format!("/api/lists/{}", list["_id"].as_str().unwrap());
+        let members_uri = format!("{}/members", list_uri);
+        let (_, entry) = send!(
+            app,
+            alice,
+            post(
+                "/api/entries",
+                json!({ "listId": list["_id"], "name": "milk" })
+            )
+        );
+        let entry_uri = format!("/api/entries/{}", entry["_id"].as_str().unwrap());
+
+        let (status, member) = send!(
+            app,
+            alice,
+            post(&members_uri, json!({ "name": "bob", "role": "viewer" }))
+        );
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        assert_eq!(member["name"], "bob", "{:?}", backend);
+        assert_eq!(member["role"], "viewer", "{:?}", backend);
+        let bob_uri = format!("{}/{}", members_uri, member["userId"].as_str().unwrap());
+
+        // a viewer sees the list, but can't change it
+        let (_, lists) = send!(app, bob, get("/api/lists"));
+        assert_eq!(lists.as_array().unwrap().len(), 1, "{:?}", backend);
+        let (_, entries) = send!(app, bob, get("/api/entries"));
+        assert_eq!(entries.as_array().unwrap().len(), 1, "{:?}", backend);
+        let (status, _) = send!(app, bob, get(&entry_uri));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        let (status, problem) = send!(app, bob, patch(&entry_uri, json!({ "done": true })));
+        assert_eq!(status, StatusCode::FORBIDDEN, "{:?}", backend);
+        assert_eq!(problem["code"], "forbidden", "{:?}", backend);
+        assert_eq!(
+            problem["details"]["requiredRole"], "editor",
+            "{:?}",
+            backend
+        );
+        let (status, _) = send!(app, bob, delete(&entry_uri));
+        assert_eq!(status, StatusCode::FORBIDDEN, "{:?}", backend);
+
+        // an editor changes entries, but can't delete the list or share it
+        let (status, member) = send!(app, alice, patch(&bob_uri, json!({ "role": "editor" })));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(member["role"], "editor", "{:?}", backend);
+        let (status, entry) = send!(app, bob, patch(&entry_uri, json!({ "done": true })));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(entry["done"], true, "{:?}", backend);
+        let (status, _) = send!(app, bob, patch(&list_uri, json!({ "name": "food" })));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        let (status, problem) = send!(app, bob, delete(&list_uri));
+        assert_eq!(status, StatusCode::FORBIDDEN, "{:?}", backend);
+        assert_eq!(problem["details"]["requiredRole"], "owner", "{:?}", backend);
+        let (status, _) = send!(app, bob, patch(&bob_uri, json!({ "role": "owner" })));
+        assert_eq!(status, StatusCode::FORBIDDEN, "{:?}", backend);
+
+        // after the access is revoked the list is gone for bob
+        let (_, members) = send!(app, alice, get(&members_uri));
+        assert_eq!(members.as_array().unwrap().len(), 1, "{:?}", backend);
+        let (status, _) = send!(app, alice, delete(&bob_uri));
+        assert_eq!(status, StatusCode::NO_CONTENT, "{:?}", backend);
+        let (status, _) = send!(app, alice, delete(&bob_uri));
+        assert_eq!(status, StatusCode::NOT_FOUND, "{:?}", backend);
+        for uri in [&list_uri, &entry_uri, &members_uri] {
+            let (status, _) = send!(app, bob, get(uri));
+            assert_eq!(status, StatusCode::NOT_FOUND, "{:?} {}", backend, uri);
+        }
+        let (_, lists) = send!(app, bob, get("/api/lists"));
+        assert_eq!(lists, json!([]), "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn only_owners_share_lists() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let alice = sign_up!(app, "alice");
+        let bob = sign_up!(app, "bob");
+        let carol = sign_up!(app, "carol");
+
+        let (_, list) = send!(
+            app,
+            alice,
+            post("/api/lists", json!({ "name": "groceries" }))
+        );
+        let members_uri = format!("/api/lists/{}/members", list["_id"].as_str().unwrap());
+
+        // strangers don't learn that the list exists
+        let (status, _) = send!(
+            app,
+            bob,
+            post(&members_uri, json!({ "name": "bob", "role": "owner" }))
+        );
+        assert_eq!(status, StatusCode::NOT_FOUND, "{:?}", backend);
+
+        let (status, problem) = send!(
+            app,
+            alice,
+            post(&members_uri, json!({ "name": "dave", "role": "viewer" }))
+        );
+        assert_eq!(status, StatusCode::NOT_FOUND, "{:?}", backend);
+        assert_eq!(problem["code"], "user_not_found", "{:?}", backend);
+        let (status, problem) = send!(
+            app,
+            alice,
+            post(&members_uri, json!({ "name": "alice", "role": "viewer" }))
+        );
+        assert_eq!(status, StatusCode::CONFLICT, "{:?}", backend);
+        assert_eq!(problem["code"], "member_already_exists", "{:?}", backend);
+
+        let (status, _) = send!(
+            app,
+            alice,
+            post(&members_uri, json!({ "name": "bob", "role": "owner" }))
+        );
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        let (status, _) = send!(
+            app,
+            alice,
+            post(&members_uri, json!({ "name": "bob", "role": "viewer" }))
+        );
+        assert_eq!(status, StatusCode::CONFLICT, "{:?}", backend);
+
+        // an owner by membership shares the list further, an editor doesn't
+        let (status, member) = send!(
+            app,
+            bob,
+            post(&members_uri, json!({ "name": "carol", "role": "editor" }))
+        );
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        let carol_uri = format!("{}/{}", members_uri, member["userId"].as_str().unwrap());
+        let (status, _) = send!(
+            app,
+            carol,
+            post(&members_uri, json!({ "name": "dave", "role": "viewer" }))
+        );
+        assert_eq!(status, StatusCode::FORBIDDEN, "{:?}", backend);
+
+        // members may leave on their own
+        let (status, _) = send!(app, carol, delete(&carol_uri));
+        assert_eq!(status, StatusCode::NO_CONTENT, "{:?}", backend);
+        let (_, members) = send!(app, alice, get(&members_uri));
+        assert_eq!(members.as_array().unwrap().len(), 1, "{:?}", backend);
+        assert_eq!(members[0]["name"], "bob", "{:?}", backend);
+
+        // deleting the list ends its memberships
+        let list_uri = format!("/api/lists/{}", list["_id"].as_str().unwrap());
+        let (status, _) = send!(app, alice, delete(&list_uri));
+        assert_eq!(status, StatusCode::NO_CONTENT, "{:?}", backend);
+        let (_, lists) = send!(app, bob, get("/api/lists"));
+        assert_eq!(lists, json!([]), "{:?}", backend);
+    }
+}
@@ -0,0 +1,73 @@
+//! Both storage backends keep lists and entries the same way: what is written survives
+//! a restart and deleting a list deletes its entries with it.
+
+#[macro_use]
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+use tempfile::TempDir;
+
+use common::{delete, get, patch, post, BACKENDS};
+
+#[actix_web::test]
+async fn lists_and_entries_survive_a_restart() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "groceries");
+        let request = post("/api/entries", json!({ "listId": list_id, "name": "milk" }));
+        let (_, entry) = send!(app, token, request);
+        let entry_uri = format!("/api/entries/{}", entry["_id"].as_str().unwrap());
+        let (status, entry) = send!(app, token, patch(&entry_uri, json!({ "done": true })));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        let list_uri = format!("/api/lists/{}/entries", list_id);
+        let (_, before) = send!(app, token, get(&list_uri));
+        drop(app);
+
+        let app = init_app!(backend, dir);
+        let (status, after) = send!(app, token, get(&list_uri));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(after, before, "{:?}", backend);
+        let (_, found) = send!(app, token, get(&entry_uri));
+        assert_eq!(found, entry, "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn deleting_a_list_deletes_its_entries() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let groceries = post_list!(app, token, "groceries");
+        let chores = post_list!(app, token, "chores");
+        let mut entry_ids = Vec::new();
+        for (list_id, name) in [
+            (&groceries, "milk"),
+            (&groceries, "eggs"),
+            (&chores, "dishes"),
+        ] {
+            let request = post("/api/entries", json!({ "listId": list_id, "name": name }));
+            let (_, entry) = send!(app, token, request);
+            entry_ids.push(entry["_id"].as_str().unwrap().to_string());
+        }
+
+        let (status, _) = send!(app, token, delete(&format!("/api/lists/{}", groceries)));
+        assert_eq!(status, StatusCode::NO_CONTENT, "{:?}", backend);
+        for entry_id in &entry_ids[..2] {
+            let (status, problem) = send!(app, token, get(&format!("/api/entries/{}", entry_id)));
+            assert_eq!(status, StatusCode::NOT_FOUND, "{:?}", backend);
+            assert_eq!(problem["code"], "entry_not_found", "{:?}", backend);
+        }
+        let (_, entries) = send!(app, token, get("/api/entries"));
+        let names: Vec<&str> = entries
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["dishes"], "{:?}", backend);
+    }
+}
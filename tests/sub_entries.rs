@@ -0,0 +1,187 @@
+//! Entries can have sub-entries within their list, shown as trees with the progress of
+//! each parent, on both storage backends. Deleting a parent deletes its sub-entries.
+
+#[macro_use]
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use common::{delete, get, patch, post, BACKENDS};
+
+/// The name of the tree's root, its progress and the trees of its children the same way.
+fn outline(tree: &Value) -> Value {
+    let children: Vec<Value> = tree["children"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(outline)
+        .collect();
+    json!([tree["parent"]["name"], tree["progress"], children])
+}
+
+/// Adds the entry and returns its id.
+macro_rules! post_entry {
+    ($app:expr, $token:expr, $body:expr) => {{
+        let (status, entry) = send!($app, $token, post("/api/entries", $body));
+        assert_eq!(status, StatusCode::CREATED);
+        entry["_id"].as_str().unwrap().to_string()
+    }};
+}
+
+#[actix_web::test]
+async fn trees_show_the_sub_entries_and_the_progress_of_their_parents() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "trip");
+        let pack = post_entry!(app, token, json!({ "listId": list_id, "name": "pack" }));
+        let clothes = post_entry!(
+            app,
+            token,
+            json!({ "listId": list_id, "parentId": pack, "name": "clothes" })
+        );
+        post_entry!(
+            app,
+            token,
+            json!({ "listId": list_id, "parentId": clothes, "name": "socks", "done": true })
+        );
+        post_entry!(
+            app,
+            token,
+            json!({ "listId": list_id, "parentId": pack, "name": "passport", "done": true })
+        );
+        post_entry!(
+            app,
+            token,
+            json!({ "listId": list_id, "name": "book hotel" })
+        );
+
+        let (status, tree) = send!(app, token, get(&format!("/api/entries/{}/tree", pack)));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        let expected = json!([
+            "pack",
+            { "done": 1, "total": 2 },
+            [
+                ["clothes", { "done": 1, "total": 1 }, [["socks", { "done": 0, "total": 0 }, []]]],
+                ["passport", { "done": 0, "total": 0 }, []],
+            ],
+        ]);
+        assert_eq!(outline(&tree), expected, "{:?}", backend);
+
+        let (status, list) = send!(app, token, get(&format!("/api/lists/{}/tree", list_id)));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(list["parent"]["name"], "trip", "{:?}", backend);
+        let top_level: Vec<Value> = list["children"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(outline)
+            .collect();
+        assert_eq!(top_level[0], expected, "{:?}", backend);
+        assert_eq!(
+            top_level[1],
+            json!(["book hotel", { "done": 0, "total": 0 }, []]),
+            "{:?}",
+            backend
+        );
+    }
+}
+
+#[actix_web::test]
+async fn a_parent_has_to_be_another_entry_of_the_same_list_and_not_below_it() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "trip");
+        let other_list_id = post_list!(app, token, "chores");
+        let pack = post_entry!(app, token, json!({ "listId": list_id, "name": "pack" }));
+        let clothes = post_entry!(
+            app,
+            token,
+            json!({ "listId": list_id, "parentId": pack, "name": "clothes" })
+        );
+        let dishes = post_entry!(
+            app,
+            token,
+            json!({ "listId": other_list_id, "name": "dishes" })
+        );
+
+        let pack_uri = format!("/api/entries/{}", pack);
+        let cases = [
+            (
+                post(
+                    "/api/entries",
+                    json!({ "listId": list_id, "parentId": dishes, "name": "towel" }),
+                ),
+                "other_list",
+            ),
+            (patch(&pack_uri, json!({ "parentId": pack })), "cycle"),
+            (patch(&pack_uri, json!({ "parentId": clothes })), "cycle"),
+        ];
+        for (request, code) in cases {
+            let (status, problem) = send!(app, token, request);
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{:?}", backend);
+            let error = &problem["details"]["errors"][0];
+            assert_eq!(error["field"], "parentId", "{:?}", backend);
+            assert_eq!(error["code"], code, "{:?}", backend);
+        }
+        let request = post(
+            "/api/entries",
+            json!({ "listId": list_id, "parentId": "nothing", "name": "towel" }),
+        );
+        let (status, problem) = send!(app, token, request);
+        assert_eq!(status, StatusCode::NOT_FOUND, "{:?}", backend);
+        assert_eq!(problem["code"], "entry_not_found", "{:?}", backend);
+
+        // moving an entry to another list leaves its parent behind
+        let clothes_uri = format!("/api/entries/{}", clothes);
+        let request = patch(&clothes_uri, json!({ "listId": other_list_id }));
+        let (status, moved) = send!(app, token, request);
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert!(moved.get("parentId").is_none(), "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn deleting_a_parent_deletes_its_sub_entries() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "trip");
+        let pack = post_entry!(app, token, json!({ "listId": list_id, "name": "pack" }));
+        let clothes = post_entry!(
+            app,
+            token,
+            json!({ "listId": list_id, "parentId": pack, "name": "clothes" })
+        );
+        let socks = post_entry!(
+            app,
+            token,
+            json!({ "listId": list_id, "parentId": clothes, "name": "socks" })
+        );
+        post_entry!(
+            app,
+            token,
+            json!({ "listId": list_id, "name": "book hotel" })
+        );
+
+        let (status, _) = send!(app, token, delete(&format!("/api/entries/{}", pack)));
+        assert_eq!(status, StatusCode::NO_CONTENT, "{:?}", backend);
+        for id in [&pack, &clothes, &socks] {
+            let (status, _) = send!(app, token, get(&format!("/api/entries/{}", id)));
+            assert_eq!(status, StatusCode::NOT_FOUND, "{:?}", backend);
+        }
+        let (_, list) = send!(app, token, get(&format!("/api/lists/{}/entries", list_id)));
+        assert_eq!(
+            list["children"].as_array().unwrap().len(),
+            1,
+            "{:?}",
+            backend
+        );
+    }
+}
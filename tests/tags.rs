@@ -0,0 +1,108 @@
+//! Tags on both storage backends, each user only sees and uses their own.
+
+#[macro_use]
+mod common;
+
+use actix_web::http::StatusCode;
+use serde_json::json;
+use tempfile::TempDir;
+use todo_list_backend::{
+    prototype_db::WriteError,
+    storage::{self, StoreError},
+};
+
+use common::{delete, get, patch, post, put, tag, user, BACKENDS};
+
+fn assert_duplicate_name<T>(result: Result<T, StoreError>, backend: storage::StorageBackend) {
+    match result {
+        Err(StoreError::Write(WriteError::Duplicate {
+            collection, index, ..
+        })) => assert_eq!((collection, index), ("tag", "name"), "{:?}", backend),
+        Err(e) => panic!("{:?}: expected a duplicate name, got {}", backend, e),
+        Ok(_) => panic!("{:?}: expected a duplicate name", backend),
+    }
+}
+
+#[test]
+fn the_store_rejects_tag_names_the_owner_has_taken_ignoring_case() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let store = storage::open(backend, dir.path().to_str().unwrap(), None).unwrap();
+        store.append_user(user("alice")).unwrap();
+        store.append_user(user("bob")).unwrap();
+        store.append_tag(tag("1", Some("alice"), "Ärger")).unwrap();
+        store.append_tag(tag("2", Some("alice"), "home")).unwrap();
+
+        assert_duplicate_name(store.append_tag(tag("3", Some("alice"), "ärger")), backend);
+        assert_duplicate_name(
+            store.patch_tag("2", &mut |tag| tag.name = "ÄRGER".to_string()),
+            backend,
+        );
+        assert_eq!(store.find_tag("2").unwrap().unwrap().name, "home");
+        let found = store.find_tag_by_name("alice", "ärger").unwrap().unwrap();
+        assert_eq!(found.id, "1", "{:?}", backend);
+
+        // other users have names of their own
+        store.append_tag(tag("4", Some("bob"), "ärger")).unwrap();
+        let found = store.find_tag_by_name("bob", "Ärger").unwrap().unwrap();
+        assert_eq!(found.id, "4", "{:?}", backend);
+
+        // tags without an owner share their names
+        store.append_tag(tag("5", None, "ärger")).unwrap();
+        assert_duplicate_name(store.append_tag(tag("6", None, "ÄRGER")), backend);
+    }
+}
+
+#[actix_web::test]
+async fn tags_of_other_users_are_hidden() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let alice = sign_up!(app, "alice");
+        let bob = sign_up!(app, "bob");
+
+        let (status, tag) = send!(app, alice, post("/api/tags", json!({ "name": "urgent" })));
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        let tag_id = tag["_id"].as_str().unwrap();
+        let tag_uri = format!("/api/tags/{}", tag_id);
+        let list_id = post_list!(app, bob, "chores");
+        let request = post(
+            "/api/entries",
+            json!({ "listId": list_id, "name": "dishes" }),
+        );
+        let (_, entry) = send!(app, bob, request);
+        let entry_id = entry["_id"].as_str().unwrap();
+
+        let (_, tags) = send!(app, bob, get("/api/tags"));
+        assert_eq!(tags, json!([]), "{:?}", backend);
+        let requests = [
+            get(&tag_uri),
+            patch(&tag_uri, json!({ "name": "mine" })),
+            delete(&tag_uri),
+            put(
+                &format!("/api/entries/{}/tags/{}", entry_id, tag_id),
+                json!({}),
+            ),
+        ];
+        for request in requests {
+            let (status, problem) = send!(app, bob, request);
+            assert_eq!(status, StatusCode::NOT_FOUND, "{:?}", backend);
+            assert_eq!(problem["code"], "tag_not_found", "{:?}", backend);
+        }
+        let (status, entries) = send!(app, bob, get("/api/entries?tag=urgent"));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(entries, json!([]), "{:?}", backend);
+
+        // the name is only taken among alice's tags
+        let (status, _) = send!(app, bob, post("/api/tags", json!({ "name": "Urgent" })));
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        let request = post("/api/tags", json!({ "name": "URGENT" }));
+        let (status, problem) = send!(app, alice, request);
+        assert_eq!(status, StatusCode::CONFLICT, "{:?}", backend);
+        assert_eq!(problem["details"]["value"], "URGENT", "{:?}", backend);
+
+        let (status, tag) = send!(app, alice, get(&tag_uri));
+        assert_eq!(status, StatusCode::OK, "{:?}", backend);
+        assert_eq!(tag["name"], "urgent", "{:?}", backend);
+    }
+}
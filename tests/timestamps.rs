@@ -0,0 +1,141 @@
+//! The server sets `createdAt` and `updatedAt` of lists and entries, on both storage backends.
+//! Lists stored before there were timestamps get the start of the epoch.
+
+#[macro_use]
+mod common;
+
+use std::{fs, time::Duration};
+
+use actix_web::{http::StatusCode, rt::time::sleep};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+use todo_list_backend::storage::{self, StorageBackend};
+
+use common::{get, patch, post, put, BACKENDS};
+
+fn timestamp(value: &Value) -> DateTime<Utc> {
+    value.as_str().unwrap().parse().unwrap()
+}
+
+/// Long enough for the clock to move on between two requests.
+async fn tick() {
+    sleep(Duration::from_millis(5)).await;
+}
+
+#[actix_web::test]
+async fn creating_sets_both_and_changing_only_the_update() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let before = Utc::now();
+        let (_, list) = send!(
+            app,
+            token,
+            post("/api/lists", json!({ "name": "groceries" }))
+        );
+        let list_id = list["_id"].as_str().unwrap().to_string();
+        let request = post("/api/entries", json!({ "listId": list_id, "name": "milk" }));
+        let (_, entry) = send!(app, token, request);
+        for created in [&list, &entry] {
+            assert!(timestamp(&created["createdAt"]) >= before, "{:?}", backend);
+            assert_eq!(created["createdAt"], created["updatedAt"], "{:?}", backend);
+        }
+
+        tick().await;
+        let list_uri = format!("/api/lists/{}", list_id);
+        let entry_uri = format!("/api/entries/{}", entry["_id"].as_str().unwrap());
+        let changes = [
+            (list.clone(), patch(&list_uri, json!({ "name": "food" }))),
+            (list, put(&list_uri, json!({ "name": "shopping" }))),
+            (entry.clone(), patch(&entry_uri, json!({ "done": true }))),
+            (
+                entry,
+                put(
+                    &entry_uri,
+                    json!({ "listId": list_id, "name": "oat milk", "done": true }),
+                ),
+            ),
+        ];
+        for (original, request) in changes {
+            let (status, changed) = send!(app, token, request);
+            assert_eq!(status, StatusCode::OK, "{:?}", backend);
+            assert_eq!(changed["createdAt"], original["createdAt"], "{:?}", backend);
+            assert!(
+                timestamp(&changed["updatedAt"]) > timestamp(&original["updatedAt"]),
+                "{:?}",
+                backend
+            );
+        }
+    }
+}
+
+#[actix_web::test]
+async fn only_what_changed_since_is_listed() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let groceries = post_list!(app, token, "groceries");
+        post_list!(app, token, "chores");
+        let mut entry_uris = Vec::new();
+        for name in ["milk", "eggs"] {
+            let request = post("/api/entries", json!({ "listId": groceries, "name": name }));
+            let (_, entry) = send!(app, token, request);
+            entry_uris.push(format!("/api/entries/{}", entry["_id"].as_str().unwrap()));
+        }
+
+        tick().await;
+        let since = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true);
+        tick().await;
+        let request = patch(&entry_uris[1], json!({ "done": true }));
+        send!(app, token, request);
+        let request = patch(
+            &format!("/api/lists/{}", groceries),
+            json!({ "name": "food" }),
+        );
+        send!(app, token, request);
+
+        let (_, lists) = send!(
+            app,
+            token,
+            get(&format!("/api/lists?updatedSince={}", since))
+        );
+        let names: Vec<&Value> = lists
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| &l["name"])
+            .collect();
+        assert_eq!(names, [&json!("food")], "{:?}", backend);
+        let uri = format!("/api/entries?updatedSince={}", since);
+        let (_, entries) = send!(app, token, get(&uri));
+        let names: Vec<&Value> = entries
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| &e["name"])
+            .collect();
+        assert_eq!(names, [&json!("eggs")], "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn lists_from_before_there_were_timestamps_start_at_the_epoch() {
+    let dir = TempDir::new().unwrap();
+    let app = init_app!(StorageBackend::Json, dir);
+    let token = sign_up!(app, "alice");
+    drop(app);
+    let legacy = json!({ "count": 1, "data": [{ "_id": "groceries", "name": "groceries" }] });
+    fs::write(dir.path().join("list.json"), legacy.to_string()).unwrap();
+    let store = storage::open(StorageBackend::Json, dir.path().to_str().unwrap(), None).unwrap();
+    storage::claim_ownerless(&*store, "alice").unwrap();
+    drop(store);
+
+    let app = init_app!(StorageBackend::Json, dir);
+    let (status, list) = send!(app, token, get("/api/lists/groceries"));
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(list["createdAt"], "1970-01-01T00:00:00Z");
+    assert_eq!(list["updatedAt"], "1970-01-01T00:00:00Z");
+}
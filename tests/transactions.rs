@@ -0,0 +1,214 @@
+//! `Database::transaction` changes several collections all or nothing.
+//! What it changed is persisted once the collections are unlocked again.
+
+mod common;
+
+use std::{
+    fs, io,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+    thread,
+};
+
+use tempfile::TempDir;
+use todo_list_backend::{
+    prototype_db::{Database, WriteError, ID_INDEX},
+    storage::{EntryStore, ListStore, StoreError, TagStore},
+};
+
+use common::{entry, list, tag};
+
+fn open(dir: &TempDir) -> Database {
+    Database::new(dir.path().to_str().unwrap().to_string(), None).unwrap()
+}
+
+/// A list with a tagged entry, so deleting the list changes three collections.
+fn seed(database: &Database) {
+    database.append_list(list("groceries")).unwrap();
+    database.append_entry(entry("milk", "groceries")).unwrap();
+    database.append_tag(tag("dairy", None, "dairy")).unwrap();
+    database.tag_entry("milk", "dairy").unwrap();
+}
+
+fn assert_seeded(database: &Database) {
+    assert!(database.find_list("groceries").unwrap().is_some());
+    assert_eq!(database.find_entries_of_list("groceries").unwrap().len(), 1);
+    assert_eq!(database.find_tags_of_entry("milk").unwrap().len(), 1);
+}
+
+#[test]
+fn keeps_the_changes_when_persisting_fails_and_writes_them_with_the_next_change() {
+    let dir = TempDir::new().unwrap();
+    let database = open(&dir);
+    seed(&database);
+
+    // the list and its entries are persisted, the assignments of the tags can't be
+    // written while their temporary file is in the way
+    let blocker = dir.path().join("entry_tag.json.tmp");
+    fs::create_dir(&blocker).unwrap();
+    assert!(database.delete_list("groceries").is_err());
+    assert!(database.find_list("groceries").unwrap().is_none());
+    assert!(database.find_tags_of_entry("milk").unwrap().is_empty());
+
+    fs::remove_dir(&blocker).unwrap();
+    database.append_list(list("chores")).unwrap();
+    database.append_entry(entry("broom", "chores")).unwrap();
+    database.tag_entry("broom", "dairy").unwrap();
+    drop(database);
+
+    let database = open(&dir);
+    assert!(database.find_list("groceries").unwrap().is_none());
+    assert!(database.find_entry("milk").unwrap().is_none());
+    assert!(database.find_tags_of_entry("milk").unwrap().is_empty());
+    assert_eq!(database.find_tags_of_entry("broom").unwrap().len(), 1);
+}
+
+#[test]
+fn rolls_back_when_the_transaction_fails() {
+    let dir = TempDir::new().unwrap();
+    let database = open(&dir);
+    seed(&database);
+
+    let result: Result<(), WriteError> = database.transaction(|transaction| {
+        transaction.lists.delete_one_by_key(ID_INDEX, "groceries")?;
+        transaction
+            .entries
+            .delete_many(|model| model.list_id == "groceries")?;
+        Err(WriteError::Io(io::Error::other("changed my mind")))
+    });
+    assert!(result.is_err());
+    assert_seeded(&database);
+
+    drop(database);
+    assert_seeded(&open(&dir));
+}
+
+#[test]
+fn rolls_back_when_the_transaction_panics_and_keeps_working() {
+    let dir = TempDir::new().unwrap();
+    let database = open(&dir);
+    seed(&database);
+
+    let panicked = panic::catch_unwind(AssertUnwindSafe(|| {
+        let _: Result<(), WriteError> = database.transaction(|transaction| {
+            transaction.lists.delete_one_by_key(ID_INDEX, "groceries")?;
+            transaction
+                .entries
+                .delete_many(|model| model.list_id == "groceries")?;
+            panic!("a bug in the middle of a transaction");
+        });
+    }));
+    assert!(panicked.is_err());
+    assert_seeded(&database);
+
+    database.append_list(list("chores")).unwrap();
+    assert!(database.find_list("chores").unwrap().is_some());
+    drop(database);
+    assert_seeded(&open(&dir));
+}
+
+#[test]
+fn concurrent_cascades_do_not_deadlock() {
+    let dir = TempDir::new().unwrap();
+    let database = Arc::new(open(&dir));
+    database.append_tag(tag("dairy", None, "dairy")).unwrap();
+
+    let threads: Vec<_> = (0..4)
+        .map(|thread| {
+            let database = database.clone();
+            thread::spawn(move || {
+                for round in 0..25 {
+                    let list_id = format!("list-{}-{}", thread, round);
+                    let entry_id = format!("entry-{}-{}", thread, round);
+                    database.append_list(list(&list_id)).unwrap();
+                    database.append_entry(entry(&entry_id, &list_id)).unwrap();
+                    database.tag_entry(&entry_id, "dairy").unwrap();
+                    database
+                        .patch_entry(&entry_id, &mut |model| model.done = true)
+                        .unwrap();
+                    if round % 2 == 0 {
+                        database.delete_entry(&entry_id).unwrap();
+                    }
+                    database.delete_list(&list_id).unwrap();
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert!(database.find_tag("dairy").unwrap().is_some());
+    assert!(database
+        .query_entries(&Default::default(), Default::default(), Default::default())
+        .unwrap()
+        .items
+        .is_empty());
+}
+
+#[test]
+fn refuses_to_refer_to_lists_entries_and_tags_that_are_gone() {
+    let dir = TempDir::new().unwrap();
+    let database = open(&dir);
+    seed(&database);
+    database.append_list(list("chores")).unwrap();
+    database.delete_list("chores").unwrap();
+
+    let assert_missing = |result: Result<_, StoreError>, expected: (&str, &str)| match result {
+        Err(StoreError::Write(WriteError::Missing { collection, id })) => {
+            assert_eq!((collection, id.as_str()), expected)
+        }
+        Err(e) => panic!("expected {:?} to be missing, got {}", expected, e),
+        Ok(_) => panic!("expected {:?} to be missing", expected),
+    };
+    assert_missing(
+        database.append_entry(entry("broom", "chores")).map(|_| ()),
+        ("list", "chores"),
+    );
+    assert_missing(
+        database.put_entry(entry("broom", "chores")).map(|_| ()),
+        ("list", "chores"),
+    );
+    assert_missing(
+        database
+            .patch_entry("milk", &mut |model| model.list_id = "chores".to_string())
+            .map(|_| ()),
+        ("list", "chores"),
+    );
+    assert_missing(
+        database.tag_entry("broom", "dairy").map(|_| ()),
+        ("entry", "broom"),
+    );
+    assert_missing(
+        database.tag_entry("milk", "fresh").map(|_| ()),
+        ("tag", "fresh"),
+    );
+
+    assert_seeded(&database);
+    assert!(database.find_entry("broom").unwrap().is_none());
+}
+
+#[test]
+fn keeps_the_entry_as_it_was_when_its_follow_up_cannot_be_added() {
+    let dir = TempDir::new().unwrap();
+    let database = open(&dir);
+    seed(&database);
+
+    let result = database.patch_entry_with_follow_up(
+        "milk",
+        &mut |model| model.done = true,
+        &mut |_, new| Some(entry(&format!("{}-next", new.id), "chores")),
+    );
+    assert!(result.is_err());
+    assert!(!database.find_entry("milk").unwrap().unwrap().done);
+    assert!(database.find_entry("milk-next").unwrap().is_none());
+
+    let result = database.patch_entry_with_follow_up(
+        "milk",
+        &mut |model| model.done = true,
+        &mut |_, new| Some(entry(&format!("{}-next", new.id), "groceries")),
+    );
+    assert!(result.unwrap().unwrap().done);
+    assert_eq!(database.find_entries_of_list("groceries").unwrap().len(), 2);
+    assert_eq!(database.find_tags_of_entry("milk-next").unwrap().len(), 1);
+}
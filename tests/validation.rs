@@ -0,0 +1,149 @@
+//! Request bodies are trimmed and checked before they reach the store, every invalid field
+//! is reported at once with 422, on both storage backends.
+
+#[macro_use]
+mod common;
+
+use actix_web::{http::StatusCode, test};
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+use common::{get, patch, post, put, with_token, BACKENDS};
+
+/// The field and code of each error the problem lists.
+fn field_errors(problem: &Value) -> Vec<(&str, &str)> {
+    problem["details"]["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| {
+            (
+                error["field"].as_str().unwrap(),
+                error["code"].as_str().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[actix_web::test]
+async fn names_are_trimmed() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+
+        let request = post("/api/lists", json!({ "name": "  groceries\n" }));
+        let (status, list) = send!(app, token, request);
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        assert_eq!(list["name"], "groceries", "{:?}", backend);
+        let request = post(
+            "/api/entries",
+            json!({ "listId": list["_id"], "name": "\tmilk " }),
+        );
+        let (status, entry) = send!(app, token, request);
+        assert_eq!(status, StatusCode::CREATED, "{:?}", backend);
+        assert_eq!(entry["name"], "milk", "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn invalid_fields_are_all_reported_at_once() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+        let list_id = post_list!(app, token, "groceries");
+        let (_, entry) = send!(
+            app,
+            token,
+            post("/api/entries", json!({ "listId": list_id, "name": "milk" }))
+        );
+        let entry_uri = format!("/api/entries/{}", entry["_id"].as_str().unwrap());
+        let too_long = "x".repeat(201);
+
+        let cases = [
+            (
+                post("/api/lists", json!({ "name": "   " })),
+                vec![("name", "empty")],
+            ),
+            (
+                put(
+                    &format!("/api/lists/{}", list_id),
+                    json!({ "name": too_long }),
+                ),
+                vec![("name", "too_long")],
+            ),
+            (
+                post(
+                    "/api/entries",
+                    json!({ "listId": "x".repeat(65), "name": "", "notes": "x".repeat(10_001) }),
+                ),
+                vec![
+                    ("listId", "too_long"),
+                    ("name", "empty"),
+                    ("notes", "too_long"),
+                ],
+            ),
+            (
+                patch(&entry_uri, json!({ "name": " ", "parentId": "" })),
+                vec![("parentId", "empty"), ("name", "empty")],
+            ),
+            (
+                put(
+                    &entry_uri,
+                    json!({ "listId": list_id, "name": too_long, "done": false }),
+                ),
+                vec![("name", "too_long")],
+            ),
+        ];
+        for (request, expected) in cases {
+            let (status, problem) = send!(app, token, request);
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{:?}", backend);
+            assert_eq!(problem["code"], "validation_failed", "{:?}", backend);
+            assert_eq!(field_errors(&problem), expected, "{:?}", backend);
+        }
+
+        // nothing was changed by any of them
+        let (_, found) = send!(app, token, get(&entry_uri));
+        assert_eq!(found, entry, "{:?}", backend);
+    }
+}
+
+#[actix_web::test]
+async fn unreadable_bodies_are_problems_too() {
+    for backend in BACKENDS {
+        let dir = TempDir::new().unwrap();
+        let app = init_app!(backend, dir);
+        let token = sign_up!(app, "alice");
+
+        let cases = [
+            (
+                test::TestRequest::post()
+                    .uri("/api/lists")
+                    .insert_header(("content-type", "application/json"))
+                    .set_payload("{ \"name\": "),
+                StatusCode::BAD_REQUEST,
+                "malformed_json",
+            ),
+            (
+                post("/api/lists", json!({ "name": 5 })),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "invalid_payload",
+            ),
+            (
+                test::TestRequest::post()
+                    .uri("/api/lists")
+                    .insert_header(("content-type", "text/plain"))
+                    .set_payload("groceries"),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_content_type",
+            ),
+        ];
+        for (request, status, code) in cases {
+            let response = test::call_service(&app, with_token(request, &token).to_request()).await;
+            assert_eq!(response.status(), status, "{:?} {}", backend, code);
+            let problem: Value = test::read_body_json(response).await;
+            assert_eq!(problem["code"], code, "{:?}", backend);
+        }
+    }
+}